@@ -0,0 +1,127 @@
+//! Solver cost on the reference scenes at fixed resolutions: the `f64` CPU reference, and every
+//! GPU precision if there is an adapter. GPU samples are timestamp query times of the solver pass
+//! where the adapter has `TIMESTAMP_QUERY`, wall-clock times until the queue is done otherwise.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use gravity_basins::{
+    cpu,
+    gpu::{self, AdapterChoice},
+    scene::Scene,
+    shader::Data,
+    timing::GpuTimer,
+    BasinRenderer, Precision,
+};
+use spirv_std::glam::dvec2;
+use std::{
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const SCENES: [(&str, &str); 3] = [
+    ("three", "scenes/three.json"),
+    ("triangle", "tests/scenes/triangle.json"),
+    ("unequal", "tests/scenes/unequal.json"),
+];
+const CPU_SIZE: u32 = 128;
+const GPU_SIZE: u32 = 512;
+
+fn view(path: &str, size: u32) -> Data {
+    let scene = Scene::load(&Path::new(env!("CARGO_MANIFEST_DIR")).join(path)).unwrap();
+    let mut data = scene.to_data().unwrap();
+    scene
+        .camera(dvec2(size as f64, size as f64))
+        .apply(&mut data, size, size);
+    data
+}
+
+fn cpu(c: &mut Criterion) {
+    let mut group = c.benchmark_group(format!("cpu {CPU_SIZE}x{CPU_SIZE}"));
+    group.sample_size(10);
+    for (name, path) in SCENES {
+        let data = view(path, CPU_SIZE);
+        group.bench_function(name, |b| b.iter(|| cpu::render(&data)));
+    }
+    group.finish();
+}
+
+fn device() -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    pollster::block_on(async {
+        let adapter = gpu::select_adapter(
+            &gpu::instance(),
+            &AdapterChoice::default(),
+            None,
+            [GPU_SIZE, GPU_SIZE],
+        )
+        .await?;
+        eprintln!("Benchmarking on {}", gpu::describe(&adapter));
+        gpu::request_device(&adapter).await
+    })
+}
+
+/// Solve the view once, waiting for the GPU
+fn solve(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    renderer: &BasinRenderer,
+    timer: Option<&mut GpuTimer>,
+    precision: Precision,
+) -> Duration {
+    let start = Instant::now();
+    let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+        label: Some("Bench Encoder"),
+    });
+    let timestamp_writes = timer.as_deref().and_then(GpuTimer::timestamp_writes);
+    renderer.compute_timed(&mut encoder, precision, timestamp_writes);
+    queue.submit(std::iter::once(encoder.finish()));
+    match timer {
+        Some(timer) => {
+            timer.start_readback(device, queue);
+            timer.wait(device).unwrap_or_default()
+        }
+        None => {
+            device
+                .poll(wgpu::PollType::Wait)
+                .expect("Failed to wait for the GPU");
+            start.elapsed()
+        }
+    }
+}
+
+fn gpu(c: &mut Criterion) {
+    let (device, queue) = match device() {
+        Ok(device) => device,
+        Err(error) => {
+            eprintln!("{error:#}, skipping the GPU benchmarks");
+            return;
+        }
+    };
+    let mut timer = GpuTimer::new(&device, &queue);
+    let mut renderer = BasinRenderer::new(
+        &device,
+        &queue,
+        wgpu::TextureFormat::Rgba8Unorm,
+        GPU_SIZE,
+        GPU_SIZE,
+    );
+    let mut group = c.benchmark_group(format!("gpu {GPU_SIZE}x{GPU_SIZE}"));
+    for (name, path) in SCENES {
+        renderer.set_data(&view(path, GPU_SIZE));
+        for precision in [Precision::F32, Precision::DoubleFloat, Precision::F64] {
+            if !renderer.supports(precision) {
+                continue;
+            }
+            let id = BenchmarkId::new(format!("{precision:?}"), name);
+            group.bench_function(id, |b| {
+                b.iter_custom(|iterations| {
+                    (0..iterations)
+                        .map(|_| solve(&device, &queue, &renderer, timer.as_mut(), precision))
+                        .sum()
+                })
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, cpu, gpu);
+criterion_main!(benches);
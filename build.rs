@@ -1,8 +1,32 @@
-use spirv_builder::{MetadataPrintout, SpirvBuilder};
+use spirv_builder::{Capability, MetadataPrintout, SpirvBuilder};
+use std::{env, error::Error, fs, path::Path};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    SpirvBuilder::new("shader", "spirv-unknown-vulkan1.4")
-        .print_metadata(MetadataPrintout::Full)
+fn main() -> Result<(), Box<dyn Error>> {
+    let out_dir = env::var("OUT_DIR")?;
+    build_shader(
+        SpirvBuilder::new("shader", "spirv-unknown-vulkan1.4"),
+        "shader.spv",
+        out_dir.as_ref(),
+    )?;
+    // the native f64 solver lives in its own module, as `Float64` is only available on some adapters
+    build_shader(
+        SpirvBuilder::new("shader", "spirv-unknown-vulkan1.4")
+            .capability(Capability::Float64)
+            .shader_crate_features(["f64".to_owned()]),
+        "shader_f64.spv",
+        out_dir.as_ref(),
+    )?;
+    Ok(())
+}
+
+/// Build a shader module and expose its path as the env var `name`.
+/// Both variants share the spirv-builder target dir, so the module is copied out before the next build overwrites it.
+fn build_shader(builder: SpirvBuilder, name: &str, out_dir: &Path) -> Result<(), Box<dyn Error>> {
+    let result = builder
+        .print_metadata(MetadataPrintout::DependencyOnly)
         .build()?;
+    let path = out_dir.join(name);
+    fs::copy(result.module.unwrap_single(), &path)?;
+    println!("cargo:rustc-env={name}={}", path.display());
     Ok(())
 }
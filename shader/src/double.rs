@@ -0,0 +1,233 @@
+//! Native `f64` solver, used by `cs_main_f64` and by the CPU reference renderer.
+//!
+//! Graviton positions and masses stay `f32` in `Data`, only the particle state is `f64`.
+
+use spirv_std::{
+    glam::{dvec2, DVec2, Vec2, Vec4},
+    num_traits::Float,
+};
+
+use crate::{ftle, uncertainty, Capture, ComputeMode, Data, Integrator, NO_GRAVITON};
+
+/// World units per pixel
+fn scale(data: &Data) -> f64 {
+    data.scale_hi as f64 + data.scale_lo as f64
+}
+
+/// World position of the pixel `offset` pixels away from the view center
+pub fn pixel_to_world(offset: Vec2, data: &Data) -> DVec2 {
+    let center = dvec2(
+        data.center_x_hi as f64 + data.center_x_lo as f64,
+        data.center_y_hi as f64 + data.center_y_lo as f64,
+    );
+    center + offset.as_dvec2() * scale(data)
+}
+
+/// `f64` version of `crate::solve`
+pub fn solve(offset: Vec2, data: &Data) -> (Vec4, Vec2) {
+    let start = pixel_to_world(offset, data);
+    if data.parameter_space != 0 {
+        // the parameters themselves are `f32` in `Data`
+        let scene = data.with_parameters(start.as_vec2());
+        let capture = find_basin(dvec2(data.start_x as f64, data.start_y as f64), &scene);
+        return (capture.to_texel(), capture.velocity);
+    }
+    if data.compute_mode == ComputeMode::FTLE {
+        let (capture, exponent) = find_basin_ftle(start, data);
+        let mut texel = capture.to_texel();
+        texel.w = exponent;
+        return (texel, capture.velocity);
+    }
+    let capture = find_basin(start, data);
+    let mut texel = capture.to_texel();
+    if data.compute_mode == ComputeMode::UNCERTAINTY {
+        let shift = dvec2(data.epsilon as f64 * scale(data), 0.);
+        texel.w = uncertainty(
+            capture,
+            [
+                find_basin(start - shift, data),
+                find_basin(start + shift, data),
+            ],
+        );
+    }
+    (texel, capture.velocity)
+}
+
+/// A test particle, released at rest
+#[derive(Clone, Copy)]
+pub struct Particle {
+    pub coord: DVec2,
+    pub velocity: DVec2,
+    pub time: f64,
+}
+impl Particle {
+    pub fn new(coord: DVec2) -> Self {
+        Self {
+            coord,
+            velocity: DVec2::ZERO,
+            time: 0.,
+        }
+    }
+
+    /// Advance by one step, unless a graviton already captured the particle.
+    /// Returns the index of that graviton, or `NO_GRAVITON`
+    pub fn step(&mut self, data: &Data) -> u32 {
+        let (graviton, min_distance_squared) = self.nearest_graviton(data);
+        if graviton == NO_GRAVITON {
+            self.advance(data.step_size(min_distance_squared) as f64, data);
+        }
+        graviton
+    }
+
+    /// `f64` version of `crate::nearest_graviton`
+    pub fn nearest_graviton(&self, data: &Data) -> (u32, f32) {
+        let mut min_distance_squared = f32::MAX;
+        for i in 0..data.num_gravitons {
+            let graviton = data.gravitons[i as usize];
+            let graviton_pos = dvec2(graviton.position_x as f64, graviton.position_y as f64);
+
+            let distance_squared = graviton_pos.distance_squared(self.coord) as f32;
+            min_distance_squared = min_distance_squared.min(distance_squared);
+            if distance_squared < data.capture_radius_squared() {
+                return (i, distance_squared);
+            }
+        }
+        (NO_GRAVITON, min_distance_squared)
+    }
+
+    /// Integrate over `dt`, whether or not the particle was captured
+    pub fn advance(&mut self, dt: f64, data: &Data) {
+        [self.coord, self.velocity] = integrate(self.coord, self.velocity, dt, data);
+        self.time += dt;
+    }
+
+    /// Kinetic plus potential energy per unit mass
+    pub fn energy(&self, data: &Data) -> f64 {
+        let mut potential = 0.;
+        for i in 0..data.num_gravitons {
+            let graviton = data.gravitons[i as usize];
+            let graviton_pos = dvec2(graviton.position_x as f64, graviton.position_y as f64);
+            potential -= graviton.mass as f64 / graviton_pos.distance(self.coord);
+        }
+        0.5 * self.velocity.length_squared() + potential
+    }
+}
+
+/// `f64` version of `crate::find_basin`
+pub fn find_basin(coord: DVec2, data: &Data) -> Capture {
+    let mut particle = Particle::new(coord);
+    let mut steps = 0;
+    let mut graviton = NO_GRAVITON;
+    while steps < data.max_steps {
+        graviton = particle.step(data);
+        if graviton != NO_GRAVITON {
+            break;
+        }
+        steps += 1;
+    }
+    Capture {
+        graviton,
+        steps,
+        time: particle.time as f32,
+        velocity: particle.velocity.as_vec2(),
+    }
+}
+
+/// `f64` version of `crate::find_basin_ftle`
+pub fn find_basin_ftle(coord: DVec2, data: &Data) -> (Capture, f32) {
+    let separation = data.epsilon as f64 * scale(data);
+    let mut particle = Particle::new(coord);
+    let mut shadow = Particle::new(coord + dvec2(separation, 0.));
+    let mut log_growth = 0.;
+    let mut steps = 0;
+    let mut graviton = NO_GRAVITON;
+    while steps < data.max_steps {
+        let min_distance_squared;
+        (graviton, min_distance_squared) = particle.nearest_graviton(data);
+        if graviton != NO_GRAVITON {
+            break;
+        }
+        let dt = data.step_size(min_distance_squared) as f64;
+        particle.advance(dt, data);
+        shadow.advance(dt, data);
+        steps += 1;
+
+        // separation in phase space
+        let offset = shadow.coord - particle.coord;
+        let velocity_offset = shadow.velocity - particle.velocity;
+        let distance = (offset.length_squared() + velocity_offset.length_squared()).sqrt();
+        if distance > 0. {
+            log_growth += (distance / separation).ln();
+            let factor = separation / distance;
+            shadow.coord = particle.coord + offset * factor;
+            shadow.velocity = particle.velocity + velocity_offset * factor;
+        }
+    }
+    let capture = Capture {
+        graviton,
+        steps,
+        time: particle.time as f32,
+        velocity: particle.velocity.as_vec2(),
+    };
+    (capture, ftle(log_growth as f32, particle.time as f32))
+}
+
+/// `f64` version of `crate::integrate`
+pub fn integrate(coord: DVec2, velocity: DVec2, dt: f64, data: &Data) -> [DVec2; 2] {
+    if data.integrator == Integrator::VERLET {
+        let half_step = velocity + accel(coord, data) * (dt * 0.5);
+        let coord = coord + half_step * dt;
+        [coord, half_step + accel(coord, data) * (dt * 0.5)]
+    } else if data.integrator == Integrator::EULER {
+        let velocity = velocity + accel(coord, data) * dt;
+        [coord + velocity * dt, velocity]
+    } else {
+        rk4_step(coord, velocity, dt, data)
+    }
+}
+
+/// `f64` version of `crate::accel`
+pub fn accel(coord: DVec2, data: &Data) -> DVec2 {
+    let mut acceleration = DVec2::ZERO;
+    for i in 0..data.num_gravitons {
+        let graviton = data.gravitons[i as usize];
+        let graviton_pos = dvec2(graviton.position_x as f64, graviton.position_y as f64);
+        let vector = graviton_pos - coord;
+
+        let distance_squared = graviton_pos.distance_squared(coord);
+        let distance = distance_squared.sqrt();
+        acceleration += vector * (graviton.mass as f64 / (distance_squared * distance));
+    }
+    acceleration
+}
+
+/// `f64` version of `crate::rk4_step`
+pub fn rk4_step(coord: DVec2, velocity: DVec2, dt: f64, data: &Data) -> [DVec2; 2] {
+    // k1
+    let a1 = accel(coord, data);
+    let p1 = velocity;
+
+    // k2
+    let pos2 = coord + p1 * (dt * 0.5);
+    let vel2 = velocity + a1 * (dt * 0.5);
+    let a2 = accel(pos2, data);
+    let p2 = vel2;
+
+    // k3
+    let pos3 = coord + p2 * (dt * 0.5);
+    let vel3 = velocity + a2 * (dt * 0.5);
+    let a3 = accel(pos3, data);
+    let p3 = vel3;
+
+    // k4
+    let pos4 = coord + p3 * dt;
+    let vel4 = velocity + a3 * dt;
+    let a4 = accel(pos4, data);
+    let p4 = vel4;
+
+    // Combine increments
+    let pos_inc = (p1 + p2 * 2.0 + p3 * 2.0 + p4) * (dt / 6.0);
+    let vel_inc = (a1 + a2 * 2.0 + a3 * 2.0 + a4) * (dt / 6.0);
+
+    [coord + pos_inc, velocity + vel_inc]
+}
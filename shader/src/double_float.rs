@@ -0,0 +1,443 @@
+//! Double-float arithmetic: a value is the unevaluated sum of two `f32`s, which gives about 48 bits
+//! of mantissa on adapters without the `Float64` capability.
+//!
+//! The error-free transformations below rely on the compiler not reassociating the float ops,
+//! products use `mul_add` so contraction into fma can't break them either.
+
+use core::ops::{Add, Mul, Neg, Sub};
+
+use spirv_std::{
+    glam::{vec2, Vec2, Vec4},
+    num_traits::Float,
+};
+
+use crate::{ftle, uncertainty, Capture, ComputeMode, Data, Integrator, NO_GRAVITON};
+
+#[derive(Clone, Copy, Default)]
+pub struct Df {
+    pub hi: f32,
+    pub lo: f32,
+}
+impl Df {
+    pub const ZERO: Self = Self { hi: 0., lo: 0. };
+
+    pub fn from_f32(value: f32) -> Self {
+        Self { hi: value, lo: 0. }
+    }
+
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn from_f64(value: f64) -> Self {
+        let hi = value as f32;
+        Self {
+            hi,
+            lo: (value - hi as f64) as f32,
+        }
+    }
+
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn to_f64(self) -> f64 {
+        self.hi as f64 + self.lo as f64
+    }
+
+    pub fn sqrt(self) -> Self {
+        if self.hi <= 0. {
+            return Self::ZERO;
+        }
+        // one newton iteration on top of the f32 estimate
+        let x = self.hi.sqrt();
+        let residual = self - two_prod(x, x);
+        quick_two_sum(x, residual.hi / (2. * x))
+    }
+}
+impl Add for Df {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        let s = two_sum(self.hi, rhs.hi);
+        let t = two_sum(self.lo, rhs.lo);
+        let s = quick_two_sum(s.hi, s.lo + t.hi);
+        quick_two_sum(s.hi, s.lo + t.lo)
+    }
+}
+impl Neg for Df {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Self {
+            hi: -self.hi,
+            lo: -self.lo,
+        }
+    }
+}
+impl Sub for Df {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self + -rhs
+    }
+}
+impl Mul for Df {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        let p = two_prod(self.hi, rhs.hi);
+        quick_two_sum(p.hi, p.lo + (self.hi * rhs.lo + self.lo * rhs.hi))
+    }
+}
+impl Mul<f32> for Df {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        self * Df::from_f32(rhs)
+    }
+}
+
+/// `a + b` exactly, for any `a` and `b`
+fn two_sum(a: f32, b: f32) -> Df {
+    let s = a + b;
+    let bb = s - a;
+    Df {
+        hi: s,
+        lo: (a - (s - bb)) + (b - bb),
+    }
+}
+
+/// `a + b` exactly, if `|a| >= |b|`
+fn quick_two_sum(a: f32, b: f32) -> Df {
+    let s = a + b;
+    Df {
+        hi: s,
+        lo: b - (s - a),
+    }
+}
+
+/// `a * b` exactly
+fn two_prod(a: f32, b: f32) -> Df {
+    let p = a * b;
+    Df {
+        hi: p,
+        lo: a.mul_add(b, -p),
+    }
+}
+
+#[derive(Clone, Copy, Default)]
+pub struct Df2 {
+    pub x: Df,
+    pub y: Df,
+}
+impl Df2 {
+    pub const ZERO: Self = Self {
+        x: Df::ZERO,
+        y: Df::ZERO,
+    };
+
+    pub fn from_vec2(value: Vec2) -> Self {
+        Self {
+            x: Df::from_f32(value.x),
+            y: Df::from_f32(value.y),
+        }
+    }
+
+    /// Truncate to `f32`, only use this for values near zero
+    pub fn to_vec2(self) -> Vec2 {
+        vec2(self.x.hi, self.y.hi)
+    }
+
+    pub fn length_squared(self) -> Df {
+        self.x * self.x + self.y * self.y
+    }
+}
+impl Add for Df2 {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Self {
+            x: self.x + rhs.x,
+            y: self.y + rhs.y,
+        }
+    }
+}
+impl Sub for Df2 {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Self {
+            x: self.x - rhs.x,
+            y: self.y - rhs.y,
+        }
+    }
+}
+impl Mul<Df> for Df2 {
+    type Output = Self;
+    fn mul(self, rhs: Df) -> Self {
+        Self {
+            x: self.x * rhs,
+            y: self.y * rhs,
+        }
+    }
+}
+impl Mul<f32> for Df2 {
+    type Output = Self;
+    fn mul(self, rhs: f32) -> Self {
+        self * Df::from_f32(rhs)
+    }
+}
+
+/// World units per pixel
+fn scale(data: &Data) -> Df {
+    Df {
+        hi: data.scale_hi,
+        lo: data.scale_lo,
+    }
+}
+
+/// World position of the pixel `offset` pixels away from the view center
+pub fn pixel_to_world(offset: Vec2, data: &Data) -> Df2 {
+    let center = Df2 {
+        x: Df {
+            hi: data.center_x_hi,
+            lo: data.center_x_lo,
+        },
+        y: Df {
+            hi: data.center_y_hi,
+            lo: data.center_y_lo,
+        },
+    };
+    center + Df2::from_vec2(offset) * scale(data)
+}
+
+/// Double-float version of `crate::solve`
+pub fn solve(offset: Vec2, data: &Data) -> (Vec4, Vec2) {
+    let start = pixel_to_world(offset, data);
+    if data.parameter_space != 0 {
+        let scene = data.with_parameters(start.to_vec2());
+        let capture = find_basin(Df2::from_vec2(vec2(data.start_x, data.start_y)), &scene);
+        return (capture.to_texel(), capture.velocity);
+    }
+    if data.compute_mode == ComputeMode::FTLE {
+        let (capture, exponent) = find_basin_ftle(start, data);
+        let mut texel = capture.to_texel();
+        texel.w = exponent;
+        return (texel, capture.velocity);
+    }
+    let capture = find_basin(start, data);
+    let mut texel = capture.to_texel();
+    if data.compute_mode == ComputeMode::UNCERTAINTY {
+        let shift = Df2::from_vec2(vec2(data.epsilon, 0.)) * scale(data);
+        texel.w = uncertainty(
+            capture,
+            [
+                find_basin(start - shift, data),
+                find_basin(start + shift, data),
+            ],
+        );
+    }
+    (texel, capture.velocity)
+}
+
+/// Double-float version of `crate::find_basin`
+pub fn find_basin(mut coord: Df2, data: &Data) -> Capture {
+    let mut velocity = Df2::ZERO;
+    let mut time = 0.;
+    for step in 0..data.max_steps {
+        let (graviton, min_distance_squared) = nearest_graviton(coord, data);
+        if graviton != NO_GRAVITON {
+            return Capture {
+                graviton,
+                steps: step,
+                time,
+                velocity: velocity.to_vec2(),
+            };
+        }
+        let dt = data.step_size(min_distance_squared);
+        let [new_coord, new_velocity] = integrate(coord, velocity, dt, data);
+        coord = new_coord;
+        velocity = new_velocity;
+        time += dt;
+    }
+    Capture {
+        graviton: NO_GRAVITON,
+        steps: data.max_steps,
+        time,
+        velocity: velocity.to_vec2(),
+    }
+}
+
+/// Double-float version of `crate::nearest_graviton`
+fn nearest_graviton(coord: Df2, data: &Data) -> (u32, f32) {
+    let mut min_distance_squared = f32::MAX;
+    for i in 0..data.num_gravitons {
+        let graviton = data.gravitons[i as usize];
+        let graviton_pos = Df2::from_vec2(vec2(graviton.position_x, graviton.position_y));
+
+        let distance_squared = (graviton_pos - coord).length_squared().hi;
+        min_distance_squared = min_distance_squared.min(distance_squared);
+        if distance_squared < data.capture_radius_squared() {
+            return (i, distance_squared);
+        }
+    }
+    (NO_GRAVITON, min_distance_squared)
+}
+
+/// Double-float version of `crate::find_basin_ftle`
+pub fn find_basin_ftle(mut coord: Df2, data: &Data) -> (Capture, f32) {
+    let separation = data.epsilon * data.scale_hi;
+    let mut shadow = coord + Df2::from_vec2(vec2(data.epsilon, 0.)) * scale(data);
+    let mut velocity = Df2::ZERO;
+    let mut shadow_velocity = Df2::ZERO;
+    let mut time = 0.;
+    let mut log_growth = 0.;
+    let mut capture = Capture {
+        graviton: NO_GRAVITON,
+        steps: data.max_steps,
+        time: 0.,
+        velocity: Vec2::ZERO,
+    };
+    for step in 0..data.max_steps {
+        let (graviton, min_distance_squared) = nearest_graviton(coord, data);
+        if graviton != NO_GRAVITON {
+            capture.graviton = graviton;
+            capture.steps = step;
+            break;
+        }
+        let dt = data.step_size(min_distance_squared);
+        [coord, velocity] = integrate(coord, velocity, dt, data);
+        [shadow, shadow_velocity] = integrate(shadow, shadow_velocity, dt, data);
+        time += dt;
+
+        // the offsets are small, so `f32` is enough for their length
+        let offset = shadow - coord;
+        let velocity_offset = shadow_velocity - velocity;
+        let distance = (offset.length_squared().hi + velocity_offset.length_squared().hi).sqrt();
+        if distance > 0. {
+            log_growth += (distance / separation).ln();
+            let factor = separation / distance;
+            shadow = coord + offset * factor;
+            shadow_velocity = velocity + velocity_offset * factor;
+        }
+    }
+    capture.time = time;
+    capture.velocity = velocity.to_vec2();
+    (capture, ftle(log_growth, time))
+}
+
+/// Double-float version of `crate::integrate`
+pub fn integrate(coord: Df2, velocity: Df2, dt: f32, data: &Data) -> [Df2; 2] {
+    if data.integrator == Integrator::VERLET {
+        let half_step = velocity + accel(coord, data) * (dt * 0.5);
+        let coord = coord + half_step * dt;
+        [coord, half_step + accel(coord, data) * (dt * 0.5)]
+    } else if data.integrator == Integrator::EULER {
+        let velocity = velocity + accel(coord, data) * dt;
+        [coord + velocity * dt, velocity]
+    } else {
+        rk4_step(coord, velocity, dt, data)
+    }
+}
+
+/// Double-float version of `crate::accel`
+pub fn accel(coord: Df2, data: &Data) -> Df2 {
+    let mut acceleration = Df2::ZERO;
+    for i in 0..data.num_gravitons {
+        let graviton = data.gravitons[i as usize];
+        let graviton_pos = Df2::from_vec2(vec2(graviton.position_x, graviton.position_y));
+        let vector = graviton_pos - coord;
+
+        // the offset is exact now, so the magnitude only needs f32
+        let distance_squared = vector.length_squared().hi;
+        let distance = distance_squared.sqrt();
+        acceleration = acceleration + vector * (graviton.mass / (distance_squared * distance));
+    }
+    acceleration
+}
+
+/// Double-float version of `crate::rk4_step`
+pub fn rk4_step(coord: Df2, velocity: Df2, dt: f32, data: &Data) -> [Df2; 2] {
+    // k1
+    let a1 = accel(coord, data);
+    let p1 = velocity;
+
+    // k2
+    let pos2 = coord + p1 * (dt * 0.5);
+    let vel2 = velocity + a1 * (dt * 0.5);
+    let a2 = accel(pos2, data);
+    let p2 = vel2;
+
+    // k3
+    let pos3 = coord + p2 * (dt * 0.5);
+    let vel3 = velocity + a2 * (dt * 0.5);
+    let a3 = accel(pos3, data);
+    let p3 = vel3;
+
+    // k4
+    let pos4 = coord + p3 * dt;
+    let vel4 = velocity + a3 * dt;
+    let a4 = accel(pos4, data);
+    let p4 = vel4;
+
+    // Combine increments
+    let pos_inc = (p1 + p2 * 2.0 + p3 * 2.0 + p4) * (dt / 6.0);
+    let vel_inc = (a1 + a2 * 2.0 + a3 * 2.0 + a4) * (dt / 6.0);
+
+    [coord + pos_inc, velocity + vel_inc]
+}
+
+#[cfg(test)]
+mod tests {
+    extern crate std;
+
+    use super::*;
+
+    const VALUES: [f32; 8] = [1., -1.5, 3.3, 1e-8, -2.7e7, 0.1, 1e15, -7.25e-3];
+
+    /// Need more than `f32`, the results are compared with `f64` on their double-float rounding
+    const WIDE_VALUES: [f64; 6] = [
+        1. / 3.,
+        core::f64::consts::SQRT_2,
+        -123.456789012345,
+        9.87654321e6,
+        1e-5 / 7.,
+        -0.1,
+    ];
+
+    /// `value` is off from `expected` by at most `1e-13 * scale`, a few ulps of double-float
+    fn close(value: Df, expected: f64, scale: f64) -> bool {
+        (value.to_f64() - expected).abs() <= 1e-13 * scale
+    }
+
+    #[test]
+    fn error_free_transformations_are_exact() {
+        for a in VALUES {
+            for b in VALUES {
+                assert_eq!(two_sum(a, b).to_f64(), a as f64 + b as f64, "{a} + {b}");
+                assert_eq!(two_prod(a, b).to_f64(), a as f64 * b as f64, "{a} * {b}");
+                let (big, small) = if a.abs() >= b.abs() { (a, b) } else { (b, a) };
+                assert_eq!(
+                    quick_two_sum(big, small).to_f64(),
+                    big as f64 + small as f64,
+                    "{big} + {small}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn arithmetic_matches_f64() {
+        for a in WIDE_VALUES {
+            let x = Df::from_f64(a);
+            assert!(close(x, a, a.abs()));
+            for b in WIDE_VALUES {
+                let y = Df::from_f64(b);
+                let (a, b) = (x.to_f64(), y.to_f64());
+                let sum_scale = a.abs() + b.abs();
+                assert!(close(x + y, a + b, sum_scale), "{a} + {b}");
+                assert!(close(x - y, a - b, sum_scale), "{a} - {b}");
+                assert!(close(x * y, a * b, (a * b).abs()), "{a} * {b}");
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt_matches_f64() {
+        for a in WIDE_VALUES.map(f64::abs).into_iter().chain([2., 1e12, 4.]) {
+            let x = Df::from_f64(a);
+            let expected = x.to_f64().sqrt();
+            assert!(close(x.sqrt(), expected, expected), "sqrt {a}");
+        }
+        assert_eq!(Df::ZERO.sqrt().to_f64(), 0.);
+        assert_eq!(Df::from_f64(-1.).sqrt().to_f64(), 0.);
+    }
+}
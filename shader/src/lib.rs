@@ -1,31 +1,284 @@
 #![no_std]
 
+#[cfg(any(feature = "f64", not(target_arch = "spirv")))]
+pub mod double;
+pub mod double_float;
+
 use bytemuck::{Pod, Zeroable};
 use spirv_std::{
-    glam::{vec2, vec4, UVec3, Vec2, Vec3Swizzles, Vec4, Vec4Swizzles},
+    glam::{
+        ivec2, uvec2, vec2, vec3, vec4, IVec2, UVec2, UVec3, Vec2, Vec3Swizzles, Vec4, Vec4Swizzles,
+    },
     image::StorageImage2d,
     num_traits::Float,
-    spirv, Image, Sampler,
+    spirv, Image,
 };
 
+/// Length of `Data::gravitons`
+pub const MAX_GRAVITONS: usize = 32;
+
 #[derive(Pod, Zeroable, Clone, Copy, Default)]
 #[repr(C, align(16))]
 pub struct Data {
-    pub gravitons: [Graviton; 32],
+    pub gravitons: [Graviton; MAX_GRAVITONS],
     pub num_gravitons: u32,
-    /// pad to 16 bytes, see `Graviton` for details
+    /// size of the output in pixels
+    pub width: u32,
+    pub height: u32,
+    /// pad `num_gravitons`, `width` and `height` to 16 bytes, see `Graviton` for details
     _pad1: f32,
+    /// world position of the view center, split into a high and a low part (see `double_float`)
+    pub center_x_hi: f32,
+    pub center_x_lo: f32,
+    pub center_y_hi: f32,
+    pub center_y_lo: f32,
+    /// world units per pixel, split like the center
+    pub scale_hi: f32,
+    pub scale_lo: f32,
     _pad2: f32,
     _pad3: f32,
+    /// distance at which a particle counts as captured by a graviton
+    pub capture_radius: f32,
+    /// steps after which a particle counts as not captured
+    pub max_steps: u32,
+    /// bounds for the adaptive step size
+    pub min_dt: f32,
+    pub max_dt: f32,
+    /// how `fs_main` colors the result, see `ColorMode`
+    pub color_mode: u32,
+    /// capture time at which the palette used for shading ends
+    pub time_scale: f32,
+    /// how `fs_main` draws basin boundaries, see `BoundaryMode`
+    pub boundary_mode: u32,
+    /// width of drawn boundaries in pixels
+    pub boundary_thickness: u32,
+    pub boundary_color_r: f32,
+    pub boundary_color_g: f32,
+    pub boundary_color_b: f32,
+    /// what `cs_main` computes besides the capture, see `ComputeMode`
+    pub compute_mode: u32,
+    /// offset of the shifted starts in pixels, for `ComputeMode::UNCERTAINTY`
+    pub epsilon: f32,
+    /// exponent at which the palette used for `ColorMode::FTLE` ends
+    pub ftle_scale: f32,
+    /// 1 if pixels vary two scene parameters instead of the start, for a particle released from
+    /// `start_x`, `start_y`. Only basins are computed there, `compute_mode` is ignored
+    pub parameter_space: u32,
+    /// what the horizontal axis of parameter space varies, see `Parameter`
+    pub parameter_x: u32,
+    /// graviton `parameter_x` belongs to
+    pub parameter_x_graviton: u32,
+    pub parameter_y: u32,
+    pub parameter_y_graviton: u32,
+    /// parameter y units per world unit of the view, so a mass and a position can share a view
+    pub parameter_y_scale: f32,
+    pub start_x: f32,
+    pub start_y: f32,
+    /// how particles are stepped, see `Integrator`
+    pub integrator: u32,
+    /// left edge of the viewport `fs_main` draws into, in pixels of the render target
+    pub viewport_x: u32,
+}
+
+/// Values of `Data::color_mode`
+pub struct ColorMode;
+impl ColorMode {
+    /// color of the capturing graviton
+    pub const BASIN: u32 = 0;
+    /// color of the capturing graviton, darker the longer the capture took
+    pub const SHADED: u32 = 1;
+    /// capture time mapped through the palette, ignoring the basin
+    pub const CAPTURE_TIME: u32 = 2;
+    /// uncertain pixels in the boundary color on darkened basins, needs `ComputeMode::UNCERTAINTY`
+    pub const UNCERTAINTY: u32 = 3;
+    /// finite-time Lyapunov exponent mapped through the palette, needs `ComputeMode::FTLE`
+    pub const FTLE: u32 = 4;
+}
+/// Values of `Data::compute_mode`, each decides what goes into the last channel of the results
+pub struct ComputeMode;
+impl ComputeMode {
+    /// speed when captured
+    pub const BASINS: u32 = 0;
+    /// 1 if a start `epsilon` pixels to the left or right lands in a different basin, 0 otherwise
+    pub const UNCERTAINTY: u32 = 1;
+    /// finite-time Lyapunov exponent, from a shadow particle released `epsilon` pixels to the right
+    pub const FTLE: u32 = 2;
+}
+
+/// Values of `Data::parameter_x` and `Data::parameter_y`, each with the graviton it belongs to
+pub struct Parameter;
+impl Parameter {
+    pub const POSITION_X: u32 = 0;
+    pub const POSITION_Y: u32 = 1;
+    pub const MASS: u32 = 2;
+}
+
+/// Values of `Data::integrator`
+pub struct Integrator;
+impl Integrator {
+    /// classic fourth order Runge-Kutta
+    pub const RK4: u32 = 0;
+    /// velocity Verlet, second order and symplectic
+    pub const VERLET: u32 = 1;
+    /// semi-implicit Euler, first order and symplectic
+    pub const EULER: u32 = 2;
+}
+
+/// Values of `Data::boundary_mode`
+pub struct BoundaryMode;
+impl BoundaryMode {
+    pub const OFF: u32 = 0;
+    /// boundaries drawn on top of the basins
+    pub const HIGHLIGHT: u32 = 1;
+    /// boundaries on black
+    pub const ONLY: u32 = 2;
+    /// Wada points (marked with 2 in the boundary mask by the host) in the boundary color, other
+    /// boundary pixels darkened
+    pub const WADA: u32 = 3;
+    /// pixels set in the mask by `cs_difference` in the boundary color on darkened basins
+    pub const DIFFERENCE: u32 = 4;
 }
 impl Data {
-    pub fn new(gravitons: [Graviton; 32], num_gravitons: u32) -> Self {
+    pub fn new(gravitons: [Graviton; MAX_GRAVITONS], num_gravitons: u32) -> Self {
         Self {
             gravitons,
             num_gravitons,
+            scale_hi: 1.,
+            capture_radius: 100.,
+            max_steps: 1000,
+            min_dt: 0.002,
+            max_dt: 0.05,
+            color_mode: ColorMode::BASIN,
+            time_scale: 10.,
+            boundary_mode: BoundaryMode::OFF,
+            boundary_thickness: 1,
+            boundary_color_r: 1.,
+            boundary_color_g: 1.,
+            boundary_color_b: 1.,
+            compute_mode: ComputeMode::BASINS,
+            epsilon: 0.5,
+            ftle_scale: 1.,
+            parameter_x: Parameter::POSITION_X,
+            parameter_y: Parameter::POSITION_Y,
+            parameter_y_scale: 1.,
             ..Default::default()
         }
     }
+
+    /// Copy with everything that only affects `fs_main` cleared, results only have to be recomputed if this changes
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn simulation(&self) -> Self {
+        let mut data = *self;
+        for graviton in &mut data.gravitons {
+            [graviton.color_r, graviton.color_g, graviton.color_b] = [0.; 3];
+        }
+        data.color_mode = 0;
+        data.time_scale = 0.;
+        data.ftle_scale = 0.;
+        data.boundary_mode = 0;
+        data.boundary_thickness = 0;
+        data.viewport_x = 0;
+        [
+            data.boundary_color_r,
+            data.boundary_color_g,
+            data.boundary_color_b,
+        ] = [0.; 3];
+        data
+    }
+
+    pub fn capture_radius_squared(&self) -> f32 {
+        self.capture_radius * self.capture_radius
+    }
+
+    /// Step size for a particle `min_distance_squared` away from the nearest graviton
+    pub fn step_size(&self, min_distance_squared: f32) -> f32 {
+        // make bigger steps if far from any gravitons
+        (min_distance_squared.sqrt() * 0.1).clamp(self.min_dt, self.max_dt)
+    }
+
+    /// Append a graviton, returns false if there is no room left
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn add_graviton(&mut self, graviton: Graviton) -> bool {
+        self.insert_graviton(self.num_gravitons as usize, graviton)
+    }
+
+    /// Insert a graviton before `index`, returns false if there is no room left
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn insert_graviton(&mut self, index: usize, graviton: Graviton) -> bool {
+        let count = self.num_gravitons as usize;
+        if count == self.gravitons.len() {
+            return false;
+        }
+        self.gravitons.copy_within(index..count, index + 1);
+        self.gravitons[index] = graviton;
+        self.num_gravitons += 1;
+        true
+    }
+
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn remove_graviton(&mut self, index: usize) -> Graviton {
+        let count = self.num_gravitons as usize;
+        let graviton = self.gravitons[index];
+        self.gravitons.copy_within(index + 1..count, index);
+        self.gravitons[count - 1] = Graviton::default();
+        self.num_gravitons -= 1;
+        graviton
+    }
+
+    /// Set the output size and the camera, `center` and `scale` are split so the solvers can use them at more than `f32` precision
+    #[cfg(not(target_arch = "spirv"))]
+    pub fn set_view(&mut self, width: u32, height: u32, center_x: f64, center_y: f64, scale: f64) {
+        use double_float::Df;
+
+        self.width = width;
+        self.height = height;
+        let [center_x, center_y, scale] = [center_x, center_y, scale].map(Df::from_f64);
+        [self.center_x_hi, self.center_x_lo] = [center_x.hi, center_x.lo];
+        [self.center_y_hi, self.center_y_lo] = [center_y.hi, center_y.lo];
+        [self.scale_hi, self.scale_lo] = [scale.hi, scale.lo];
+    }
+
+    /// Value of `parameter` (see `Parameter`) of graviton `graviton`
+    pub fn parameter(&self, parameter: u32, graviton: u32) -> f32 {
+        let graviton = self.gravitons[graviton as usize];
+        if parameter == Parameter::POSITION_X {
+            graviton.position_x
+        } else if parameter == Parameter::POSITION_Y {
+            graviton.position_y
+        } else {
+            graviton.mass
+        }
+    }
+
+    fn set_parameter(&mut self, parameter: u32, graviton: u32, value: f32) {
+        let graviton = &mut self.gravitons[graviton as usize];
+        if parameter == Parameter::POSITION_X {
+            graviton.position_x = value;
+        } else if parameter == Parameter::POSITION_Y {
+            graviton.position_y = value;
+        } else {
+            graviton.mass = value;
+        }
+    }
+
+    /// The position space scene at the point `point` of parameter space, in world units of the view
+    pub fn with_parameters(&self, point: Vec2) -> Self {
+        let mut data = *self;
+        data.parameter_space = 0;
+        if self.parameter_x_graviton < self.num_gravitons {
+            data.set_parameter(self.parameter_x, self.parameter_x_graviton, point.x);
+        }
+        if self.parameter_y_graviton < self.num_gravitons {
+            let value = point.y * self.parameter_y_scale;
+            data.set_parameter(self.parameter_y, self.parameter_y_graviton, value);
+        }
+        data
+    }
+
+    /// Offset of `pixel` from the center of the output, in pixels
+    pub fn pixel_offset(&self, pixel: UVec2) -> Vec2 {
+        pixel.as_vec2() - vec2(self.width as f32, self.height as f32) * 0.5
+    }
 }
 
 #[derive(Pod, Zeroable, Clone, Copy, Default)]
@@ -62,6 +315,51 @@ impl Graviton {
     }
 }
 
+// The host uploads `Data` byte for byte and the shaders read it as a uniform buffer, so the
+// layout has to satisfy the uniform rules: 16 byte array stride and vectors on 16 byte bounds.
+// Adding or moving a field without fixing the padding fails here instead of garbling the
+// fields behind it.
+const _: () = {
+    use core::mem::{align_of, offset_of, size_of};
+    assert!(size_of::<Graviton>() == 32 && align_of::<Graviton>() == 16);
+    assert!(offset_of!(Graviton, position_x) == 16);
+    assert!(size_of::<Data>() == 1168 && align_of::<Data>() == 16);
+    assert!(offset_of!(Data, num_gravitons) == 32 * MAX_GRAVITONS);
+    assert!(offset_of!(Data, center_x_hi) == offset_of!(Data, num_gravitons) + 16);
+    assert!(offset_of!(Data, capture_radius) == offset_of!(Data, center_x_hi) + 32);
+    assert!(offset_of!(Data, color_mode) == offset_of!(Data, capture_radius) + 16);
+    assert!(offset_of!(Data, boundary_color_r) == offset_of!(Data, color_mode) + 16);
+    assert!(offset_of!(Data, epsilon) == offset_of!(Data, boundary_color_r) + 16);
+    assert!(offset_of!(Data, parameter_space) == offset_of!(Data, epsilon) + 8);
+    assert!(offset_of!(Data, start_x) == offset_of!(Data, parameter_space) + 24);
+    assert!(offset_of!(Data, integrator) == offset_of!(Data, start_x) + 8);
+};
+
+/// Returned by the `find_basin` functions if no graviton captured the particle
+pub const NO_GRAVITON: u32 = u32::MAX;
+
+/// Result of integrating a single particle
+#[derive(Clone, Copy)]
+pub struct Capture {
+    /// index of the capturing graviton, or `NO_GRAVITON`
+    pub graviton: u32,
+    pub steps: u32,
+    pub time: f32,
+    /// velocity when captured
+    pub velocity: Vec2,
+}
+impl Capture {
+    /// Texel of the storage texture: graviton index (-1 if not captured), steps, time, speed
+    pub fn to_texel(self) -> Vec4 {
+        let label = if self.graviton == NO_GRAVITON {
+            -1.
+        } else {
+            self.graviton as f32
+        };
+        vec4(label, self.steps as f32, self.time, self.velocity.length())
+    }
+}
+
 #[spirv(compute(threads(16, 16)))]
 pub fn cs_main(
     #[spirv(global_invocation_id)] id: UVec3,
@@ -71,43 +369,225 @@ pub fn cs_main(
         format = rgba32f,
         sampled = false
     ),
+    #[spirv(descriptor_set = 0, binding = 2)] velocities: &Image!(
+        2D,
+        format = rg32f,
+        sampled = false
+    ),
+) {
+    let solution = solve(data.pixel_offset(id.truncate()), data);
+    write_solution(output, velocities, id, solution);
+}
+
+/// Same as `cs_main`, but with double-float arithmetic for deep zooms
+#[spirv(compute(threads(16, 16)))]
+pub fn cs_main_df(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] data: &Data,
+    #[spirv(descriptor_set = 0, binding = 1)] output: &Image!(
+        2D,
+        format = rgba32f,
+        sampled = false
+    ),
+    #[spirv(descriptor_set = 0, binding = 2)] velocities: &Image!(
+        2D,
+        format = rg32f,
+        sampled = false
+    ),
+) {
+    let solution = double_float::solve(data.pixel_offset(id.truncate()), data);
+    write_solution(output, velocities, id, solution);
+}
+
+/// Same as `cs_main`, but with native `f64` arithmetic, needs the `Float64` capability
+#[cfg(feature = "f64")]
+#[spirv(compute(threads(16, 16)))]
+pub fn cs_main_f64(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] data: &Data,
+    #[spirv(descriptor_set = 0, binding = 1)] output: &Image!(
+        2D,
+        format = rgba32f,
+        sampled = false
+    ),
+    #[spirv(descriptor_set = 0, binding = 2)] velocities: &Image!(
+        2D,
+        format = rg32f,
+        sampled = false
+    ),
+) {
+    let solution = double::solve(data.pixel_offset(id.truncate()), data);
+    write_solution(output, velocities, id, solution);
+}
+
+fn write_solution(
+    output: &Image!(2D, format = rgba32f, sampled = false),
+    velocities: &Image!(2D, format = rg32f, sampled = false),
+    id: UVec3,
+    (texel, velocity): (Vec4, Vec2),
 ) {
-    const LIMIT: f32 = 100. * 100.;
-    // find where the pixel falls into
-    let mut coord = id.as_vec3().xy();
+    // no documentation for why this is unsafe (probably because of mutation through shared reference?)
+    unsafe {
+        output.write(id.truncate(), texel);
+        velocities.write(id.truncate(), velocity);
+    }
+}
+
+/// Texel of the results for the pixel `offset` pixels away from the view center, and the
+/// velocity of its particle when captured. The uncertainty and FTLE compute modes replace the
+/// speed in the texel
+pub fn solve(offset: Vec2, data: &Data) -> (Vec4, Vec2) {
+    let start = vec2(data.center_x_hi, data.center_y_hi) + offset * data.scale_hi;
+    if data.parameter_space != 0 {
+        let scene = data.with_parameters(start);
+        let capture = find_basin(vec2(data.start_x, data.start_y), &scene);
+        return (capture.to_texel(), capture.velocity);
+    }
+    if data.compute_mode == ComputeMode::FTLE {
+        let (capture, exponent) = find_basin_ftle(start, data);
+        let mut texel = capture.to_texel();
+        texel.w = exponent;
+        return (texel, capture.velocity);
+    }
+    let capture = find_basin(start, data);
+    let mut texel = capture.to_texel();
+    if data.compute_mode == ComputeMode::UNCERTAINTY {
+        let shift = vec2(data.epsilon * data.scale_hi, 0.);
+        texel.w = uncertainty(
+            capture,
+            [
+                find_basin(start - shift, data),
+                find_basin(start + shift, data),
+            ],
+        );
+    }
+    (texel, capture.velocity)
+}
+
+/// 1 if any of the `shifted` starts ended up in a different basin than `capture`
+pub fn uncertainty(capture: Capture, shifted: [Capture; 2]) -> f32 {
+    if shifted[0].graviton != capture.graviton || shifted[1].graviton != capture.graviton {
+        1.
+    } else {
+        0.
+    }
+}
+
+/// Find the graviton that captures a particle released at rest from `coord`
+pub fn find_basin(mut coord: Vec2, data: &Data) -> Capture {
     let mut velocity = Vec2::ZERO;
-    for _ in 0..1000 {
-        let mut min_distance_squared = f32::MAX;
-        for i in 0..data.num_gravitons {
-            let graviton = data.gravitons[i as usize];
-            let graviton_pos = vec2(graviton.position_x, graviton.position_y);
-
-            // calculate gravity
-            let distance_squared = graviton_pos.distance_squared(coord);
-            min_distance_squared = min_distance_squared.min(distance_squared);
-            // check if inside graviton
-            if distance_squared < LIMIT {
-                // no documentation for why this is unsafe (probably because of mutation through shared reference?)
-                unsafe {
-                    // return color
-                    output.write(
-                        id.truncate(),
-                        vec4(graviton.color_r, graviton.color_g, graviton.color_b, 1.),
-                    );
-                }
-                return;
-            }
+    let mut time = 0.;
+    for step in 0..data.max_steps {
+        let (graviton, min_distance_squared) = nearest_graviton(coord, data);
+        if graviton != NO_GRAVITON {
+            return Capture {
+                graviton,
+                steps: step,
+                time,
+                velocity,
+            };
         }
-        // make bigger steps if far from any gravitons
-        let dt = (min_distance_squared.sqrt() * 0.1).clamp(0.002, 0.05);
-        let [new_coord, new_velocity] = rk4_step(coord, velocity, dt, data);
+        let dt = data.step_size(min_distance_squared);
+        let [new_coord, new_velocity] = integrate(coord, velocity, dt, data);
         coord = new_coord;
         velocity = new_velocity;
+        time += dt;
+    }
+    Capture {
+        graviton: NO_GRAVITON,
+        steps: data.max_steps,
+        time,
+        velocity,
+    }
+}
+
+/// The graviton capturing a particle at `coord` (or `NO_GRAVITON`), and the squared distance to the nearest graviton
+fn nearest_graviton(coord: Vec2, data: &Data) -> (u32, f32) {
+    let mut min_distance_squared = f32::MAX;
+    for i in 0..data.num_gravitons {
+        let graviton = data.gravitons[i as usize];
+        let graviton_pos = vec2(graviton.position_x, graviton.position_y);
+
+        let distance_squared = graviton_pos.distance_squared(coord);
+        min_distance_squared = min_distance_squared.min(distance_squared);
+        // check if inside graviton
+        if distance_squared < data.capture_radius_squared() {
+            return (i, distance_squared);
+        }
+    }
+    (NO_GRAVITON, min_distance_squared)
+}
+
+/// `find_basin`, also following a shadow particle `epsilon` pixels away with the same step sizes.
+/// The shadow is pulled back after every step, the logs of how far it drifted add up to the
+/// finite-time Lyapunov exponent that is returned with the capture
+pub fn find_basin_ftle(mut coord: Vec2, data: &Data) -> (Capture, f32) {
+    let separation = data.epsilon * data.scale_hi;
+    let mut shadow = coord + vec2(separation, 0.);
+    let mut velocity = Vec2::ZERO;
+    let mut shadow_velocity = Vec2::ZERO;
+    let mut time = 0.;
+    let mut log_growth = 0.;
+    let mut capture = Capture {
+        graviton: NO_GRAVITON,
+        steps: data.max_steps,
+        time: 0.,
+        velocity: Vec2::ZERO,
+    };
+    for step in 0..data.max_steps {
+        let (graviton, min_distance_squared) = nearest_graviton(coord, data);
+        if graviton != NO_GRAVITON {
+            capture.graviton = graviton;
+            capture.steps = step;
+            break;
+        }
+        let dt = data.step_size(min_distance_squared);
+        [coord, velocity] = integrate(coord, velocity, dt, data);
+        [shadow, shadow_velocity] = integrate(shadow, shadow_velocity, dt, data);
+        time += dt;
+
+        // separation in phase space
+        let offset = shadow - coord;
+        let velocity_offset = shadow_velocity - velocity;
+        let distance = (offset.length_squared() + velocity_offset.length_squared()).sqrt();
+        if distance > 0. {
+            log_growth += (distance / separation).ln();
+            let factor = separation / distance;
+            shadow = coord + offset * factor;
+            shadow_velocity = velocity + velocity_offset * factor;
+        }
+    }
+    capture.time = time;
+    capture.velocity = velocity;
+    (capture, ftle(log_growth, time))
+}
+
+/// Mean exponential growth rate of a separation that grew by `exp(log_growth)` over `time`
+pub fn ftle(log_growth: f32, time: f32) -> f32 {
+    if time > 0. {
+        log_growth / time
+    } else {
+        0.
+    }
+}
+
+/// Advance a particle by `dt` with `data.integrator`
+pub fn integrate(coord: Vec2, velocity: Vec2, dt: f32, data: &Data) -> [Vec2; 2] {
+    if data.integrator == Integrator::VERLET {
+        let half_step = velocity + accel(coord, data) * (dt * 0.5);
+        let coord = coord + half_step * dt;
+        [coord, half_step + accel(coord, data) * (dt * 0.5)]
+    } else if data.integrator == Integrator::EULER {
+        // moving with the updated velocity is what keeps the energy bounded
+        let velocity = velocity + accel(coord, data) * dt;
+        [coord + velocity * dt, velocity]
+    } else {
+        rk4_step(coord, velocity, dt, data)
     }
 }
 
 /// Compute total gravitational acceleration
-fn accel(coord: Vec2, data: &Data) -> Vec2 {
+pub fn accel(coord: Vec2, data: &Data) -> Vec2 {
     let mut acceleration = Vec2::ZERO;
     for i in 0..data.num_gravitons {
         let graviton = data.gravitons[i as usize];
@@ -121,7 +601,8 @@ fn accel(coord: Vec2, data: &Data) -> Vec2 {
     acceleration
 }
 
-fn rk4_step(coord: Vec2, velocity: Vec2, dt: f32, data: &Data) -> [Vec2; 2] {
+/// Classic fourth order Runge-Kutta step, returns the new position and velocity
+pub fn rk4_step(coord: Vec2, velocity: Vec2, dt: f32, data: &Data) -> [Vec2; 2] {
     // k1
     let a1 = accel(coord, data);
     let p1 = velocity;
@@ -151,6 +632,72 @@ fn rk4_step(coord: Vec2, velocity: Vec2, dt: f32, data: &Data) -> [Vec2; 2] {
     [coord + pos_inc, velocity + vel_inc]
 }
 
+/// Mark pixels with a 4-neighbour in a different basin, this undilated mask is what analysis reads
+#[spirv(compute(threads(16, 16)))]
+pub fn cs_boundary(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] data: &Data,
+    #[spirv(descriptor_set = 0, binding = 1)] results: &Image!(2D, format = rgba32f, sampled),
+    #[spirv(descriptor_set = 0, binding = 2)] mask: &Image!(2D, format = r32f, sampled = false),
+) {
+    let pixel = id.truncate();
+    if pixel.x >= data.width || pixel.y >= data.height {
+        return;
+    }
+    let label = |pixel: UVec2| -> f32 {
+        let result: Vec4 = results.fetch(pixel);
+        result.x
+    };
+    let center = label(pixel);
+    let max = uvec2(data.width, data.height) - 1;
+    let is_boundary = (pixel.x > 0 && label(pixel - uvec2(1, 0)) != center)
+        || (pixel.x < max.x && label(pixel + uvec2(1, 0)) != center)
+        || (pixel.y > 0 && label(pixel - uvec2(0, 1)) != center)
+        || (pixel.y < max.y && label(pixel + uvec2(0, 1)) != center);
+
+    let value = if is_boundary { 1. } else { 0. };
+    unsafe {
+        mask.write(pixel, vec4(value, 0., 0., 0.));
+    }
+}
+
+/// Mark pixels whose label differs between `results` and `other`, two solutions of the same view
+#[spirv(compute(threads(16, 16)))]
+pub fn cs_difference(
+    #[spirv(global_invocation_id)] id: UVec3,
+    #[spirv(uniform, descriptor_set = 0, binding = 0)] data: &Data,
+    #[spirv(descriptor_set = 0, binding = 1)] results: &Image!(2D, format = rgba32f, sampled),
+    #[spirv(descriptor_set = 0, binding = 2)] other: &Image!(2D, format = rgba32f, sampled),
+    #[spirv(descriptor_set = 0, binding = 3)] mask: &Image!(2D, format = r32f, sampled = false),
+) {
+    let pixel = id.truncate();
+    if pixel.x >= data.width || pixel.y >= data.height {
+        return;
+    }
+    let result: Vec4 = results.fetch(pixel);
+    let other_result: Vec4 = other.fetch(pixel);
+    let value = if result.x != other_result.x { 1. } else { 0. };
+    unsafe {
+        mask.write(pixel, vec4(value, 0., 0., 0.));
+    }
+}
+
+/// Largest value of the boundary mask within `boundary_thickness / 2` of `pixel`
+fn boundary_value(mask: &Image!(2D, format = r32f, sampled), pixel: UVec2, data: &Data) -> f32 {
+    // the mask already covers one pixel on each side of a boundary, so thickness 1 and 2 need no dilation
+    let radius = (data.boundary_thickness / 2) as i32;
+    let max = ivec2(data.width as i32, data.height as i32) - 1;
+    let mut largest = 0.;
+    for y in -radius..=radius {
+        for x in -radius..=radius {
+            let neighbour = (pixel.as_ivec2() + ivec2(x, y)).clamp(IVec2::ZERO, max);
+            let value: Vec4 = mask.fetch(neighbour.as_uvec2());
+            largest = value.x.max(largest);
+        }
+    }
+    largest
+}
+
 #[spirv(vertex)]
 pub fn vs_main(#[spirv(vertex_index)] idx: u32, #[spirv(position, invariant)] out_pos: &mut Vec4) {
     // generate full-screen triangle
@@ -159,12 +706,88 @@ pub fn vs_main(#[spirv(vertex_index)] idx: u32, #[spirv(position, invariant)] ou
     *out_pos = vec4(x, y, 0., 1.);
 }
 
+/// Color the results of `cs_main`, so colors can change without recomputing
 #[spirv(fragment)]
 pub fn fs_main(
     #[spirv(frag_coord)] coord: Vec4,
     #[spirv(descriptor_set = 0, binding = 0)] texture: &Image!(2D, format = rgba32f, sampled),
-    #[spirv(descriptor_set = 0, binding = 1)] sampler: &Sampler,
+    #[spirv(descriptor_set = 0, binding = 1)] palette: &Image!(1D, format = rgba32f, sampled),
+    #[spirv(uniform, descriptor_set = 0, binding = 2)] data: &Data,
+    #[spirv(descriptor_set = 0, binding = 3)] boundaries: &Image!(2D, format = r32f, sampled),
     out_color: &mut Vec4,
 ) {
-    *out_color = texture.sample(*sampler, coord.xy());
+    let pixel = coord.xy().as_uvec2() - uvec2(data.viewport_x, 0);
+    let result: Vec4 = texture.fetch(pixel);
+    let label = result.x;
+    let time = (result.z / data.time_scale).clamp(0., 1.);
+
+    let boundary_color = vec4(
+        data.boundary_color_r,
+        data.boundary_color_g,
+        data.boundary_color_b,
+        1.,
+    );
+    let black = vec4(0., 0., 0., 1.);
+
+    let boundary = if data.boundary_mode == BoundaryMode::OFF {
+        0.
+    } else {
+        boundary_value(boundaries, pixel, data)
+    };
+
+    *out_color = if data.boundary_mode == BoundaryMode::WADA && boundary > 1.5 {
+        boundary_color
+    } else if data.boundary_mode == BoundaryMode::WADA && boundary > 0.5 {
+        (boundary_color.xyz() * 0.35).extend(1.)
+    } else if boundary > 0.5 {
+        boundary_color
+    } else if data.boundary_mode == BoundaryMode::ONLY {
+        black
+    } else if data.color_mode == ColorMode::UNCERTAINTY && result.w > 0.5 {
+        boundary_color
+    } else if data.color_mode == ColorMode::CAPTURE_TIME {
+        palette_color(palette, time)
+    } else if data.color_mode == ColorMode::FTLE {
+        palette_color(palette, (result.w / data.ftle_scale).clamp(0., 1.))
+    } else if label < 0. {
+        black
+    } else {
+        let graviton = data.gravitons[label as usize];
+        let brightness = if data.boundary_mode == BoundaryMode::DIFFERENCE {
+            0.3
+        } else if data.color_mode == ColorMode::SHADED {
+            1. - 0.8 * time
+        } else if data.color_mode == ColorMode::UNCERTAINTY {
+            0.3
+        } else {
+            1.
+        };
+        (vec3(graviton.color_r, graviton.color_g, graviton.color_b) * brightness).extend(1.)
+    };
+}
+
+/// Color at `t` in `0..=1` of the palette
+fn palette_color(palette: &Image!(1D, format = rgba32f, sampled), t: f32) -> Vec4 {
+    let last = PALETTE_SIZE - 1;
+    palette.fetch((t * last as f32) as u32)
+}
+
+/// Number of texels in the palette texture
+pub const PALETTE_SIZE: u32 = 256;
+
+/// Overlay lines, `position` is already in clip space
+#[spirv(vertex)]
+pub fn vs_line(
+    position: Vec2,
+    color: Vec4,
+    #[spirv(position, invariant)] out_pos: &mut Vec4,
+    out_color: &mut Vec4,
+) {
+    *out_pos = vec4(position.x, position.y, 0., 1.);
+    *out_color = color;
+}
+
+#[spirv(fragment)]
+pub fn fs_line(color: Vec4, out_color: &mut Vec4) {
+    *out_color = color;
 }
@@ -0,0 +1,185 @@
+//! Invariants of the solvers, compiled for the host
+
+use proptest::{prelude::*, sample::select};
+use shader::{
+    accel, double,
+    double_float::{self, Df2},
+    find_basin, integrate, Data, Graviton, Integrator, MAX_GRAVITONS,
+};
+use spirv_std::glam::{vec2, Vec2};
+use std::f32::consts::TAU;
+
+const CAPTURE_RADIUS: f32 = 0.1;
+
+fn scene(bodies: &[(Vec2, f32)], capture_radius: f32, integrator: u32) -> Data {
+    let mut data = Data::new([Graviton::default(); MAX_GRAVITONS], 0);
+    for &(position, mass) in bodies {
+        assert!(data.add_graviton(Graviton::new(position.x, position.y, 1., 1., 1., mass)));
+    }
+    data.capture_radius = capture_radius;
+    data.integrator = integrator;
+    data
+}
+
+fn coordinate() -> impl Strategy<Value = f32> {
+    -3f32..3.
+}
+
+fn point() -> impl Strategy<Value = Vec2> {
+    (coordinate(), coordinate()).prop_map(|(x, y)| vec2(x, y))
+}
+
+fn mass() -> impl Strategy<Value = f32> {
+    0.5f32..2.
+}
+
+fn integrator() -> impl Strategy<Value = u32> {
+    select(vec![Integrator::RK4, Integrator::VERLET, Integrator::EULER])
+}
+
+proptest! {
+    /// The pull of a lone graviton points at it with magnitude mass / distance²
+    #[test]
+    fn accel_of_lone_graviton(position in point(), mass in mass(), coord in point()) {
+        let offset = position - coord;
+        prop_assume!(offset.length() > CAPTURE_RADIUS);
+        let data = scene(&[(position, mass)], CAPTURE_RADIUS, Integrator::RK4);
+        let expected = offset.normalize() * (mass / offset.length_squared());
+        let error = (accel(coord, &data) - expected).length();
+        prop_assert!(error <= 1e-5 * expected.length(), "off by {error} from {expected}");
+    }
+
+    /// Gravitons pull independently of each other
+    #[test]
+    fn accel_adds_up(a in point(), b in point(), masses in (mass(), mass()), coord in point()) {
+        prop_assume!(a != coord && b != coord);
+        let both = scene(&[(a, masses.0), (b, masses.1)], CAPTURE_RADIUS, Integrator::RK4);
+        let a = scene(&[(a, masses.0)], CAPTURE_RADIUS, Integrator::RK4);
+        let b = scene(&[(b, masses.1)], CAPTURE_RADIUS, Integrator::RK4);
+        prop_assert_eq!(accel(coord, &both), accel(coord, &a) + accel(coord, &b));
+    }
+
+    /// Bodies on the x axis make the basins mirror symmetric about it. This holds exactly, as
+    /// negating a coordinate commutes with every rounding step
+    #[test]
+    fn mirror_symmetric_basins(
+        bodies in prop::collection::vec((coordinate(), mass()), 1..=4),
+        start in point(),
+        integrator in integrator(),
+    ) {
+        let bodies: Vec<_> = bodies.into_iter().map(|(x, mass)| (vec2(x, 0.), mass)).collect();
+        let data = scene(&bodies, CAPTURE_RADIUS, integrator);
+        let mirrored = start * vec2(1., -1.);
+
+        let [capture, mirror] = [start, mirrored].map(|start| find_basin(start, &data));
+        prop_assert_eq!((capture.graviton, capture.steps), (mirror.graviton, mirror.steps));
+
+        let [capture, mirror] =
+            [start, mirrored].map(|start| double::find_basin(start.as_dvec2(), &data));
+        prop_assert_eq!((capture.graviton, capture.steps), (mirror.graviton, mirror.steps));
+    }
+
+    /// Swapping two gravitons swaps their labels. With more than two this wouldn't be exact,
+    /// the accelerations would be summed in another order
+    #[test]
+    fn swapped_gravitons_swap_labels(
+        a in (point(), mass()),
+        b in (point(), mass()),
+        start in point(),
+        integrator in integrator(),
+    ) {
+        // both could capture at once otherwise, and the first one wins
+        prop_assume!(a.0.distance(b.0) > 2. * CAPTURE_RADIUS);
+        let data = scene(&[a, b], CAPTURE_RADIUS, integrator);
+        let swapped = scene(&[b, a], CAPTURE_RADIUS, integrator);
+        let swap = |graviton| match graviton {
+            0 => 1,
+            1 => 0,
+            _ => graviton,
+        };
+
+        prop_assert_eq!(
+            swap(find_basin(start, &data).graviton),
+            find_basin(start, &swapped).graviton
+        );
+        let start = start.as_dvec2();
+        prop_assert_eq!(
+            swap(double::find_basin(start, &data).graviton),
+            double::find_basin(start, &swapped).graviton
+        );
+    }
+
+    /// Energy stays close to its initial value over one orbit around a lone graviton, taking
+    /// the step sizes the solver would
+    #[test]
+    fn bounded_energy_drift(
+        mass in mass(),
+        radius in 0.5f32..2.,
+        // of the circular orbit speed, for ellipses of both orientations
+        speed in 0.8f32..1.2,
+        integrator in integrator(),
+    ) {
+        let data = scene(&[(Vec2::ZERO, mass)], CAPTURE_RADIUS, integrator);
+        let energy =
+            |coord: Vec2, velocity: Vec2| 0.5 * velocity.length_squared() - mass / coord.length();
+        let mut coord = vec2(radius, 0.);
+        let mut velocity = vec2(0., speed * (mass / radius).sqrt());
+        let initial = energy(coord, velocity);
+        let semi_major_axis = -mass / (2. * initial);
+        let period = TAU * (semi_major_axis.powi(3) / mass).sqrt();
+        // RK4 drifts slowly, the symplectic integrators oscillate around the initial energy
+        let bound = match integrator {
+            Integrator::VERLET => 0.05,
+            Integrator::EULER => 0.25,
+            _ => 1e-3,
+        };
+
+        let mut time = 0.;
+        while time < period {
+            let dt = data.step_size(coord.length_squared());
+            [coord, velocity] = integrate(coord, velocity, dt, &data);
+            time += dt;
+            let drift = ((energy(coord, velocity) - initial) / initial).abs();
+            prop_assert!(drift < bound, "relative drift {drift} at {time} of {period}");
+        }
+    }
+
+    /// The double-float solver finds the basin the `f64` one does for a particle released near
+    /// the first of two gravitons, well inside its basin. Near boundaries they may part ways
+    #[test]
+    fn double_float_matches_f64(
+        a in (point(), mass()),
+        b in (point(), mass()),
+        capture_radius in 0.05f32..0.2,
+        // of the separation, from the capture radius
+        distance in 0f32..0.1,
+        angle in 0f32..TAU,
+        integrator in integrator(),
+    ) {
+        let separation = a.0.distance(b.0);
+        prop_assume!(separation > 2.);
+        let data = scene(&[a, b], capture_radius, integrator);
+        let start = a.0 + Vec2::from_angle(angle) * (capture_radius + distance * separation);
+        prop_assert_eq!(
+            double_float::find_basin(Df2::from_vec2(start), &data).graviton,
+            double::find_basin(start.as_dvec2(), &data).graviton
+        );
+    }
+
+    /// A particle released at rest near a lone graviton falls into it
+    #[test]
+    fn lone_graviton_captures(
+        position in point(),
+        mass in mass(),
+        capture_radius in 0.05f32..0.5,
+        // from the capture radius
+        distance in 0.01f32..5.,
+        angle in 0f32..TAU,
+        integrator in integrator(),
+    ) {
+        let data = scene(&[(position, mass)], capture_radius, integrator);
+        let start = position + Vec2::from_angle(angle) * (capture_radius + distance);
+        prop_assert_eq!(find_basin(start, &data).graviton, 0);
+        prop_assert_eq!(double::find_basin(start.as_dvec2(), &data).graviton, 0);
+    }
+}
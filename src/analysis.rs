@@ -0,0 +1,299 @@
+//! Measurements on the result image that go beyond counting pixels
+
+/// CPU version of `cs_boundary`: pixels with a 4-neighbour in a different basin
+pub fn boundary_mask(results: &[[f32; 4]], width: usize, height: usize) -> Vec<bool> {
+    let label = |x: usize, y: usize| results[y * width + x][0];
+    let mut mask = vec![false; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let center = label(x, y);
+            mask[y * width + x] = (x > 0 && label(x - 1, y) != center)
+                || (x + 1 < width && label(x + 1, y) != center)
+                || (y > 0 && label(x, y - 1) != center)
+                || (y + 1 < height && label(x, y + 1) != center);
+        }
+    }
+    mask
+}
+
+/// Least squares line through a set of points
+#[derive(Clone, Copy, Debug)]
+pub struct LinearFit {
+    pub slope: f64,
+    /// coefficient of determination, 1 for a perfect fit
+    pub r_squared: f64,
+}
+impl LinearFit {
+    /// `None` for fewer than two distinct `x`
+    pub fn new(points: &[(f64, f64)]) -> Option<Self> {
+        let n = points.len() as f64;
+        let mean_x = points.iter().map(|&(x, _)| x).sum::<f64>() / n;
+        let mean_y = points.iter().map(|&(_, y)| y).sum::<f64>() / n;
+        let sxx: f64 = points.iter().map(|&(x, _)| (x - mean_x).powi(2)).sum();
+        let sxy: f64 = points
+            .iter()
+            .map(|&(x, y)| (x - mean_x) * (y - mean_y))
+            .sum();
+        let syy: f64 = points.iter().map(|&(_, y)| (y - mean_y).powi(2)).sum();
+        if points.len() < 2 || sxx == 0. {
+            return None;
+        }
+        Some(Self {
+            slope: sxy / sxx,
+            r_squared: if syy == 0. {
+                1.
+            } else {
+                sxy * sxy / (sxx * syy)
+            },
+        })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BoxCounting {
+    /// box size in pixels and number of boxes containing a boundary pixel
+    pub counts: Vec<(usize, usize)>,
+    /// `dimension` is the slope of `log count` over `log (1 / size)`
+    pub fit: Option<LinearFit>,
+}
+impl BoxCounting {
+    /// Count boxes of power of two sizes, from single pixels up to an eighth of the shorter side
+    pub fn new(mask: &[bool], width: usize, height: usize) -> Self {
+        let largest = (width.min(height) / 8).max(1);
+        let counts: Vec<_> = (0..)
+            .map(|exponent| 1 << exponent)
+            .take_while(|&size| size <= largest)
+            .map(|size| (size, count_boxes(mask, width, height, size)))
+            .collect();
+        let points: Vec<_> = counts
+            .iter()
+            .filter(|&&(_, count)| count > 0)
+            .map(|&(size, count)| (-(size as f64).ln(), (count as f64).ln()))
+            .collect();
+        Self {
+            fit: LinearFit::new(&points),
+            counts,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self.fit {
+            Some(fit) => format!(
+                "box-counting dimension {:.4} (R² {:.4}, {} box sizes)",
+                fit.slope,
+                fit.r_squared,
+                self.counts.len()
+            ),
+            None => "box-counting dimension undefined, no boundary in view".to_owned(),
+        }
+    }
+}
+
+/// Boxes of `size` x `size` pixels containing at least one set pixel, partial boxes at the edges count
+fn count_boxes(mask: &[bool], width: usize, height: usize, size: usize) -> usize {
+    let columns = width.div_ceil(size);
+    let mut occupied = vec![false; columns * height.div_ceil(size)];
+    for (i, _) in mask.iter().enumerate().filter(|(_, &set)| set) {
+        let (x, y) = (i % width, i / width);
+        occupied[y / size * columns + x / size] = true;
+    }
+    occupied.into_iter().filter(|&occupied| occupied).count()
+}
+
+/// Share of pixels whose shifted starts land in a different basin, see `ComputeMode::UNCERTAINTY`
+pub fn uncertain_fraction(results: &[[f32; 4]]) -> f64 {
+    let uncertain = results.iter().filter(|texel| texel[3] > 0.5).count();
+    uncertain as f64 / results.len().max(1) as f64
+}
+
+#[derive(Clone, Debug)]
+pub struct UncertaintyExponent {
+    /// epsilon in pixels and the share of uncertain pixels
+    pub fractions: Vec<(f32, f64)>,
+    /// the exponent is the slope of `log fraction` over `log epsilon`
+    pub fit: Option<LinearFit>,
+}
+impl UncertaintyExponent {
+    /// Epsilons the exponent is fitted over, in pixels
+    pub const EPSILONS: [f32; 7] = [1., 0.5, 0.25, 0.125, 0.0625, 0.03125, 0.015625];
+
+    pub fn new(fractions: Vec<(f32, f64)>) -> Self {
+        let points: Vec<_> = fractions
+            .iter()
+            .filter(|&&(_, fraction)| fraction > 0.)
+            .map(|&(epsilon, fraction)| ((epsilon as f64).ln(), fraction.ln()))
+            .collect();
+        Self {
+            fit: LinearFit::new(&points),
+            fractions,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        match self.fit {
+            // in the plane, the boundary dimension is 2 minus the exponent
+            Some(fit) => format!(
+                "uncertainty exponent {:.4} (R² {:.4}, {} epsilons, boundary dimension {:.4})",
+                fit.slope,
+                fit.r_squared,
+                self.fractions.len(),
+                2. - fit.slope
+            ),
+            None => "uncertainty exponent undefined, no uncertain pixels in view".to_owned(),
+        }
+    }
+}
+
+/// Share of boundary pixels whose neighbourhood contains every basin in view, at several radii
+#[derive(Clone, Debug)]
+pub struct Wada {
+    /// basins (not counting uncaptured pixels) in view, the check needs at least three
+    pub basins: usize,
+    pub boundary_pixels: usize,
+    /// radius of the square neighbourhood in pixels and the share of boundary pixels that are Wada points
+    pub fractions: Vec<(usize, f64)>,
+    /// Wada points at the largest radius
+    pub points: Vec<bool>,
+}
+impl Wada {
+    pub const RADII: [usize; 4] = [1, 2, 4, 8];
+
+    pub fn new(results: &[[f32; 4]], width: usize, height: usize) -> Self {
+        let labels: Vec<_> = results
+            .iter()
+            .map(|texel| (texel[0] >= 0.).then_some(texel[0] as usize))
+            .collect();
+        let mut present = [false; shader::MAX_GRAVITONS];
+        for &label in labels.iter().flatten() {
+            present[label] = true;
+        }
+        let basins = present.iter().filter(|&&present| present).count();
+        let mask = boundary_mask(results, width, height);
+        let boundary: Vec<_> = (0..mask.len()).filter(|&i| mask[i]).collect();
+
+        let mut points = vec![false; mask.len()];
+        let fractions = Self::RADII
+            .iter()
+            .map(|&radius| {
+                let mut wada_points = 0;
+                for &i in &boundary {
+                    let is_wada = basins >= 3
+                        && neighbourhood_basins(&labels, width, height, i, radius) == present;
+                    wada_points += is_wada as usize;
+                    points[i] = is_wada;
+                }
+                (radius, wada_points as f64 / boundary.len().max(1) as f64)
+            })
+            .collect();
+        Self {
+            basins,
+            boundary_pixels: boundary.len(),
+            fractions,
+            points,
+        }
+    }
+
+    pub fn summary(&self) -> String {
+        if self.basins < 3 {
+            return format!("Wada check needs three basins, {} in view", self.basins);
+        }
+        let fractions: Vec<_> = self
+            .fractions
+            .iter()
+            .map(|(radius, fraction)| format!("r{radius} {:.1}%", fraction * 100.))
+            .collect();
+        format!(
+            "Wada points of {} boundary pixels: {}",
+            self.boundary_pixels,
+            fractions.join(", ")
+        )
+    }
+}
+
+/// Basins within `radius` pixels (in both axes) of pixel `i`
+fn neighbourhood_basins(
+    labels: &[Option<usize>],
+    width: usize,
+    height: usize,
+    i: usize,
+    radius: usize,
+) -> [bool; shader::MAX_GRAVITONS] {
+    let (x, y) = (i % width, i / width);
+    let mut present = [false; shader::MAX_GRAVITONS];
+    for y in y.saturating_sub(radius)..(y + radius + 1).min(height) {
+        for x in x.saturating_sub(radius)..(x + radius + 1).min(width) {
+            if let Some(label) = labels[y * width + x] {
+                present[label] = true;
+            }
+        }
+    }
+    present
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::TAU;
+
+    const SIZE: usize = 256;
+
+    fn dimension(mask: &[bool]) -> f64 {
+        BoxCounting::new(mask, SIZE, SIZE).fit.unwrap().slope
+    }
+
+    #[test]
+    fn line_fit() {
+        let points: Vec<_> = (0..5).map(|x| (x as f64, x as f64 + 3.)).collect();
+        let fit = LinearFit::new(&points).unwrap();
+        assert!((fit.slope - 1.).abs() < 1e-12);
+        assert!((fit.r_squared - 1.).abs() < 1e-12);
+        assert!(LinearFit::new(&[(1., 1.), (1., 2.)]).is_none());
+    }
+
+    #[test]
+    fn filled_square_dimension() {
+        let inside = |i: usize| (64..192).contains(&(i % SIZE)) && (64..192).contains(&(i / SIZE));
+        let mask: Vec<_> = (0..SIZE * SIZE).map(inside).collect();
+        assert!((dimension(&mask) - 2.).abs() < 0.05);
+    }
+
+    #[test]
+    fn straight_boundary_dimension() {
+        let results: Vec<_> = (0..SIZE * SIZE)
+            .map(|i| [(i % SIZE >= SIZE / 2) as u8 as f32, 0., 0., 0.])
+            .collect();
+        let mask = boundary_mask(&results, SIZE, SIZE);
+        assert_eq!(mask.iter().filter(|&&set| set).count(), 2 * SIZE);
+        assert!((dimension(&mask) - 1.).abs() < 0.05);
+    }
+
+    #[test]
+    fn wada_points_where_three_sectors_meet() {
+        let size = 64;
+        let center = size as f64 / 2.;
+        let offset = |i: usize| {
+            let x = (i % size) as f64 + 0.5 - center;
+            let y = (i / size) as f64 + 0.5 - center;
+            (x, y)
+        };
+        let results: Vec<_> = (0..size * size)
+            .map(|i| {
+                let (x, y) = offset(i);
+                let sector = (y.atan2(x).rem_euclid(TAU) / (TAU / 3.)) as u32 % 3;
+                [sector as f32, 0., 0., 0.]
+            })
+            .collect();
+        let wada = Wada::new(&results, size, size);
+        assert_eq!(wada.basins, 3);
+
+        let distance = |i: usize| {
+            let (x, y) = offset(i);
+            x.hypot(y)
+        };
+        let points: Vec<_> = (0..size * size).filter(|&i| wada.points[i]).collect();
+        assert!(points.iter().any(|&i| distance(i) < 3.));
+        // the third sector is out of reach of the largest neighbourhood far along a boundary
+        assert!(points.iter().all(|&i| distance(i) < 20.));
+        assert!(wada.fractions.iter().all(|&(_, fraction)| fraction > 0.));
+    }
+}
@@ -0,0 +1,40 @@
+use shader::Data;
+use spirv_std::glam::DVec2;
+
+/// Maps pixels to world coordinates. Kept in `f64`, so it doesn't limit how deep the solvers can zoom
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Camera {
+    /// world position of the center of the view
+    pub center: DVec2,
+    /// world units per pixel
+    pub scale: f64,
+}
+impl Camera {
+    pub fn new(center: DVec2) -> Self {
+        Self { center, scale: 1. }
+    }
+
+    pub fn pixel_to_world(&self, pixel: DVec2, size: DVec2) -> DVec2 {
+        self.center + (pixel - size * 0.5) * self.scale
+    }
+
+    pub fn world_to_pixel(&self, world: DVec2, size: DVec2) -> DVec2 {
+        (world - self.center) / self.scale + size * 0.5
+    }
+
+    /// Zoom in by `factor`, keeping the world position under `pixel` in place
+    pub fn zoom_at(&mut self, pixel: DVec2, size: DVec2, factor: f64) {
+        let anchor = self.pixel_to_world(pixel, size);
+        self.scale /= factor;
+        self.center = anchor - (pixel - size * 0.5) * self.scale;
+    }
+
+    /// Move the view by `pixels`
+    pub fn pan(&mut self, pixels: DVec2) {
+        self.center += pixels * self.scale;
+    }
+
+    pub fn apply(&self, data: &mut Data, width: u32, height: u32) {
+        data.set_view(width, height, self.center.x, self.center.y, self.scale);
+    }
+}
@@ -0,0 +1,322 @@
+//! Command line interface, the headless commands render with the CPU reference renderer
+
+use crate::viewer::MAX_VIEW;
+use anyhow::Context;
+use clap::{Parser, Subcommand};
+use gravity_basins::{
+    analysis::{self, BoxCounting, UncertaintyExponent, Wada},
+    cpu,
+    export::{self, Format, Metadata},
+    generate::Generator,
+    gpu, hdr,
+    palette::Palette,
+    scene::Scene,
+    svg,
+};
+use shader::{ColorMode, ComputeMode, Data};
+use spirv_std::glam::dvec2;
+use std::{
+    path::{Path, PathBuf},
+    time::Instant,
+};
+
+#[derive(Parser)]
+#[command(about = "Basins of attraction of gravitating point masses")]
+pub struct Cli {
+    /// Scene file to open in the viewer
+    pub scene: Option<PathBuf>,
+    /// GPU adapter for the viewer, by index or part of the name, see `adapters`
+    #[arg(long)]
+    pub adapter: Option<String>,
+    /// Render on a software adapter such as lavapipe or llvmpipe
+    #[arg(long, conflicts_with = "adapter")]
+    pub software: bool,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// Print the box-counting dimension of the basin boundary of each scene as CSV
+    Dimension {
+        scenes: Vec<PathBuf>,
+        #[arg(long, default_value_t = 512)]
+        width: u32,
+        #[arg(long, default_value_t = 512)]
+        height: u32,
+    },
+    /// Print the uncertainty exponent of each scene as CSV, fitted over a range of epsilons
+    Uncertainty {
+        scenes: Vec<PathBuf>,
+        #[arg(long, default_value_t = 256)]
+        width: u32,
+        #[arg(long, default_value_t = 256)]
+        height: u32,
+    },
+    /// Print the share of boundary pixels that are Wada points at each radius as CSV
+    Wada {
+        scenes: Vec<PathBuf>,
+        #[arg(long, default_value_t = 512)]
+        width: u32,
+        #[arg(long, default_value_t = 512)]
+        height: u32,
+    },
+    /// Save label, steps, capture time, final velocity and, depending on the compute mode, speed,
+    /// uncertain flag or FTLE of every pixel, with a JSON sidecar
+    Export {
+        scene: PathBuf,
+        /// written files start with this path
+        #[arg(long, short, default_value = "basins")]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t)]
+        format: Format,
+        #[arg(long, default_value_t = 512)]
+        width: u32,
+        #[arg(long, default_value_t = 512)]
+        height: u32,
+    },
+    /// Render a scene and save it as OpenEXR (color plus raw channels) or 16-bit PNG
+    Image {
+        scene: PathBuf,
+        /// `.exr` or `.png`
+        #[arg(long, short, default_value = "basins.exr")]
+        output: PathBuf,
+        #[arg(long, value_enum, default_value_t = Coloring::Shaded)]
+        color: Coloring,
+        /// capture time shown as the darkest shade or the end of the palette
+        #[arg(long, default_value_t = 10.)]
+        time_scale: f32,
+        #[arg(long, default_value_t = 1024)]
+        width: u32,
+        #[arg(long, default_value_t = 1024)]
+        height: u32,
+    },
+    /// Trace the basins of a scene and save them as SVG paths
+    Svg {
+        scene: PathBuf,
+        #[arg(long, short, default_value = "basins.svg")]
+        output: PathBuf,
+        /// how far simplified outlines may deviate from the traced ones, in pixels
+        #[arg(long, default_value_t = 0.5)]
+        tolerance: f64,
+        #[arg(long, default_value_t = 1024)]
+        width: u32,
+        #[arg(long, default_value_t = 1024)]
+        height: u32,
+    },
+    /// Build a scene from a generator and save it, run again with `--base` to combine them
+    Generate {
+        /// scene the generated bodies are added to, mirror and rotate copy its bodies
+        #[arg(long)]
+        base: Option<PathBuf>,
+        #[arg(long, short, default_value = "scene.json")]
+        output: PathBuf,
+        #[command(subcommand)]
+        generator: Generator,
+    },
+    /// List the GPU adapters with the index `--adapter` takes, and whether the viewer can use them
+    Adapters,
+}
+
+/// Color modes available from the command line, see `ColorMode`
+#[derive(Clone, Copy, PartialEq, Eq, Debug, clap::ValueEnum)]
+pub enum Coloring {
+    Basin,
+    Shaded,
+    /// capture time through viridis
+    CaptureTime,
+}
+
+/// `scene` viewed at `width` x `height` with its own or a fitted camera
+pub fn view(scene: &Scene, width: u32, height: u32) -> anyhow::Result<Data> {
+    let mut data = scene.to_data()?;
+    let camera = scene.camera(dvec2(width as f64, height as f64));
+    camera.apply(&mut data, width, height);
+    Ok(data)
+}
+
+pub fn render(data: &Data) -> Vec<[f32; 4]> {
+    let start = Instant::now();
+    let results = cpu::render(data);
+    log::info!(
+        "Rendered {}x{} in {:?}",
+        data.width,
+        data.height,
+        start.elapsed()
+    );
+    results
+}
+
+pub fn dimension(scenes: &[PathBuf], width: u32, height: u32) -> anyhow::Result<()> {
+    println!("scene,dimension,r_squared,boundary_pixels");
+    for path in scenes {
+        let scene = Scene::load(path)?;
+        let results = render(&view(&scene, width, height)?);
+        let (width, height) = (width as usize, height as usize);
+        let mask = analysis::boundary_mask(&results, width, height);
+        let box_counting = BoxCounting::new(&mask, width, height);
+        log::info!("{}: {}", path.display(), box_counting.summary());
+
+        let (dimension, r_squared) = box_counting
+            .fit
+            .map_or((String::new(), String::new()), |fit| {
+                (fit.slope.to_string(), fit.r_squared.to_string())
+            });
+        let boundary_pixels = mask.iter().filter(|&&set| set).count();
+        println!(
+            "{},{dimension},{r_squared},{boundary_pixels}",
+            path.display()
+        );
+    }
+    Ok(())
+}
+
+pub fn uncertainty(scenes: &[PathBuf], width: u32, height: u32) -> anyhow::Result<()> {
+    println!("scene,exponent,r_squared");
+    for path in scenes {
+        let mut data = view(&Scene::load(path)?, width, height)?;
+        data.compute_mode = ComputeMode::UNCERTAINTY;
+        let fractions = UncertaintyExponent::EPSILONS
+            .into_iter()
+            .map(|epsilon| {
+                data.epsilon = epsilon;
+                (epsilon, analysis::uncertain_fraction(&render(&data)))
+            })
+            .collect();
+        let exponent = UncertaintyExponent::new(fractions);
+        log::info!("{}: {}", path.display(), exponent.summary());
+
+        let (exponent, r_squared) = exponent.fit.map_or((String::new(), String::new()), |fit| {
+            (fit.slope.to_string(), fit.r_squared.to_string())
+        });
+        println!("{},{exponent},{r_squared}", path.display());
+    }
+    Ok(())
+}
+
+pub fn wada(scenes: &[PathBuf], width: u32, height: u32) -> anyhow::Result<()> {
+    let radii: Vec<_> = Wada::RADII
+        .iter()
+        .map(|radius| format!("r{radius}"))
+        .collect();
+    println!("scene,basins,boundary_pixels,{}", radii.join(","));
+    for path in scenes {
+        let results = render(&view(&Scene::load(path)?, width, height)?);
+        let wada = Wada::new(&results, width as usize, height as usize);
+        log::info!("{}: {}", path.display(), wada.summary());
+
+        let fractions: Vec<_> = wada
+            .fractions
+            .iter()
+            .map(|(_, fraction)| fraction.to_string())
+            .collect();
+        println!(
+            "{},{},{},{}",
+            path.display(),
+            wada.basins,
+            wada.boundary_pixels,
+            fractions.join(",")
+        );
+    }
+    Ok(())
+}
+
+pub fn export(
+    path: &Path,
+    output: &Path,
+    format: Format,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    let scene = Scene::load(path)?;
+    let data = view(&scene, width, height)?;
+    // with the camera actually used, fitted or not
+    let scene = scene.snapshot(&scene.camera(dvec2(width as f64, height as f64)));
+    let metadata = Metadata {
+        width,
+        height,
+        precision: "f64 (CPU)",
+        scene: &scene,
+    };
+    let start = Instant::now();
+    let (results, velocities) = cpu::render_with_velocities(&data);
+    log::info!("Rendered {width}x{height} in {:?}", start.elapsed());
+    export::export(output, format, &results, &velocities, &data, &metadata)?;
+    log::info!("Exported {} to {}", path.display(), output.display());
+    Ok(())
+}
+
+pub fn svg(
+    path: &Path,
+    output: &Path,
+    tolerance: f64,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    let scene = Scene::load(path)?;
+    let data = view(&scene, width, height)?;
+    let camera = scene.camera(dvec2(width as f64, height as f64));
+    let svg = svg::basins_svg(&render(&data), width, height, &data, &camera, tolerance);
+    std::fs::write(output, svg).with_context(|| format!("Failed to write {}", output.display()))?;
+    log::info!("Saved {} as {}", path.display(), output.display());
+    Ok(())
+}
+
+pub fn image(
+    path: &Path,
+    output: &Path,
+    color: Coloring,
+    time_scale: f32,
+    width: u32,
+    height: u32,
+) -> anyhow::Result<()> {
+    // fail before rendering
+    hdr::ImageFormat::from_path(output)?;
+    let mut data = view(&Scene::load(path)?, width, height)?;
+    data.color_mode = match color {
+        Coloring::Basin => ColorMode::BASIN,
+        Coloring::Shaded => ColorMode::SHADED,
+        Coloring::CaptureTime => ColorMode::CAPTURE_TIME,
+    };
+    data.time_scale = time_scale;
+    let palettes = Palette::builtin();
+    let palette = palettes
+        .iter()
+        .find(|palette| palette.name == "viridis")
+        .unwrap_or(&palettes[0]);
+
+    let results = render(&data);
+    let colors = hdr::colorize(&results, None, &data, palette);
+    hdr::save(output, width, height, &colors, &results, &data)?;
+    log::info!("Saved {} as {}", path.display(), output.display());
+    Ok(())
+}
+
+pub fn generate(base: Option<&Path>, output: &Path, generator: &Generator) -> anyhow::Result<()> {
+    let mut scene = match base {
+        Some(path) => Scene::load(path)?,
+        None => Scene::default(),
+    };
+    generator.apply(&mut scene.bodies, &Palette::builtin()[0])?;
+    scene.save(output)?;
+    log::info!(
+        "Saved {} bodies as {}",
+        scene.bodies.len(),
+        output.display()
+    );
+    Ok(())
+}
+
+pub fn adapters() -> anyhow::Result<()> {
+    let adapters = gpu::adapters(&gpu::instance());
+    if adapters.is_empty() {
+        anyhow::bail!("No GPU adapters found, install a Vulkan driver or lavapipe");
+    }
+    for (i, adapter) in adapters.iter().enumerate() {
+        match gpu::check(adapter, MAX_VIEW) {
+            Ok(()) => println!("{i}: {}", gpu::describe(adapter)),
+            Err(error) => println!("{i}: {} - unsupported: {error}", gpu::describe(adapter)),
+        }
+    }
+    Ok(())
+}
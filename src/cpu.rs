@@ -0,0 +1,43 @@
+//! CPU reference renderer, running the `f64` solver from the shader crate for every pixel
+
+use shader::{double, Data};
+use spirv_std::glam::uvec2;
+use std::thread;
+
+/// Render `data` into texels laid out like the storage texture (see `shader::solve`)
+pub fn render(data: &Data) -> Vec<[f32; 4]> {
+    render_with_velocities(data).0
+}
+
+/// `render`, plus the velocity of every pixel's particle when captured, laid out like
+/// `BasinRenderer::read_velocities`
+pub fn render_with_velocities(data: &Data) -> (Vec<[f32; 4]>, Vec<[f32; 2]>) {
+    let (width, height) = (data.width as usize, data.height as usize);
+    let mut texels = vec![[0.; 4]; width * height];
+    let mut velocities = vec![[0.; 2]; width * height];
+    if texels.is_empty() {
+        return (texels, velocities);
+    }
+
+    let threads = thread::available_parallelism().map_or(1, |n| n.get());
+    let rows_per_thread = height.div_ceil(threads);
+    let chunk_size = rows_per_thread * width;
+    thread::scope(|scope| {
+        let chunks = texels
+            .chunks_mut(chunk_size)
+            .zip(velocities.chunks_mut(chunk_size));
+        for (chunk_index, (texels, velocities)) in chunks.enumerate() {
+            scope.spawn(move || {
+                for (i, (texel, velocity)) in texels.iter_mut().zip(velocities).enumerate() {
+                    let x = i % width;
+                    let y = chunk_index * rows_per_thread + i / width;
+                    let offset = data.pixel_offset(uvec2(x as u32, y as u32));
+                    let solution = double::solve(offset, data);
+                    *texel = solution.0.to_array();
+                    *velocity = solution.1.to_array();
+                }
+            });
+        }
+    });
+    (texels, velocities)
+}
@@ -0,0 +1,164 @@
+//! Raw per-pixel results as NumPy arrays or CSV, with a JSON sidecar describing the view
+
+use crate::scene::Scene;
+use anyhow::Context;
+use serde::Serialize;
+use shader::{ComputeMode, Data};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, clap::ValueEnum)]
+pub enum Format {
+    /// one `.npy` file per channel, shaped (height, width)
+    #[default]
+    Npy,
+    /// a single table with one row per pixel
+    Csv,
+}
+
+/// Element types `write_npy` can store
+trait Element: Copy {
+    /// NumPy dtype string, little endian
+    const DESCR: &'static str;
+    fn le_bytes(self) -> [u8; 4];
+}
+impl Element for i32 {
+    const DESCR: &'static str = "<i4";
+    fn le_bytes(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+impl Element for u32 {
+    const DESCR: &'static str = "<u4";
+    fn le_bytes(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+impl Element for f32 {
+    const DESCR: &'static str = "<f4";
+    fn le_bytes(self) -> [u8; 4] {
+        self.to_le_bytes()
+    }
+}
+
+/// NPY format version 1.0, row-major
+fn write_npy<T: Element>(path: &Path, width: u32, height: u32, values: &[T]) -> anyhow::Result<()> {
+    let mut header = format!(
+        "{{'descr': '{}', 'fortran_order': False, 'shape': ({height}, {width}), }}",
+        T::DESCR
+    );
+    // magic, version and header length take 10 bytes, the header ends in a newline
+    // and is padded so the data starts at a multiple of 64 bytes
+    let padding = (64 - (10 + header.len() + 1) % 64) % 64;
+    header.push_str(&" ".repeat(padding));
+    header.push('\n');
+
+    let mut bytes = Vec::with_capacity(10 + header.len() + values.len() * 4);
+    bytes.extend_from_slice(b"\x93NUMPY\x01\x00");
+    bytes.extend_from_slice(&(header.len() as u16).to_le_bytes());
+    bytes.extend_from_slice(header.as_bytes());
+    for &value in values {
+        bytes.extend_from_slice(&value.le_bytes());
+    }
+    fs::write(path, bytes).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// What the exported results show, written to the JSON sidecar
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    pub width: u32,
+    pub height: u32,
+    /// arithmetic the results were computed with
+    pub precision: &'a str,
+    pub scene: &'a Scene,
+}
+
+#[derive(Serialize)]
+struct Sidecar<'a> {
+    #[serde(flatten)]
+    metadata: &'a Metadata<'a>,
+    compute_mode: &'static str,
+    /// only meaningful for the uncertainty mode
+    epsilon: f32,
+    /// files next to the sidecar, by channel
+    files: BTreeMap<&'static str, String>,
+}
+
+/// Write `results` (see `Capture::to_texel`) and `velocities` computed from `data` next to
+/// `prefix`: `<prefix>_labels.npy` and friends or `<prefix>.csv`, plus `<prefix>.json`
+pub fn export(
+    prefix: &Path,
+    format: Format,
+    results: &[[f32; 4]],
+    velocities: &[[f32; 2]],
+    data: &Data,
+    metadata: &Metadata,
+) -> anyhow::Result<()> {
+    let (width, height) = (metadata.width, metadata.height);
+    assert_eq!(results.len(), velocities.len());
+    // the fourth channel depends on the compute mode, in basins mode `Capture::to_texel` writes
+    // the length of the final velocity
+    let (compute_mode, extra) = match data.compute_mode {
+        ComputeMode::UNCERTAINTY => ("uncertainty", "uncertain"),
+        ComputeMode::FTLE => ("ftle", "ftle"),
+        _ => ("basins", "speed"),
+    };
+    let file = |suffix: &str| {
+        let mut name = prefix.as_os_str().to_owned();
+        name.push(suffix);
+        PathBuf::from(name)
+    };
+
+    let mut files = BTreeMap::new();
+    match format {
+        Format::Npy => {
+            let labels: Vec<i32> = results.iter().map(|texel| texel[0] as i32).collect();
+            let steps: Vec<u32> = results.iter().map(|texel| texel[1] as u32).collect();
+            let channel = |i: usize| results.iter().map(|texel| texel[i]).collect::<Vec<_>>();
+            let velocity = |i: usize| velocities.iter().map(|v| v[i]).collect::<Vec<_>>();
+            let paths =
+                ["labels", "steps", "time", "velocity_x", "velocity_y", extra].map(|name| {
+                    let path = file(&format!("_{name}.npy"));
+                    files.insert(name, file_name(&path));
+                    path
+                });
+            write_npy(&paths[0], width, height, &labels)?;
+            write_npy(&paths[1], width, height, &steps)?;
+            write_npy(&paths[2], width, height, &channel(2))?;
+            write_npy(&paths[3], width, height, &velocity(0))?;
+            write_npy(&paths[4], width, height, &velocity(1))?;
+            write_npy(&paths[5], width, height, &channel(3))?;
+        }
+        Format::Csv => {
+            let mut csv = format!("x,y,label,steps,time,velocity_x,velocity_y,{extra}\n");
+            let rows = results.iter().zip(velocities).enumerate();
+            for (i, (&[label, steps, time, w], &[vx, vy])) in rows {
+                let (x, y) = (i as u32 % width, i as u32 / width);
+                let (label, steps) = (label as i32, steps as u32);
+                writeln!(csv, "{x},{y},{label},{steps},{time},{vx},{vy},{w}").unwrap();
+            }
+            let path = file(".csv");
+            fs::write(&path, csv).with_context(|| format!("Failed to write {}", path.display()))?;
+            files.insert("table", file_name(&path));
+        }
+    }
+
+    let sidecar = Sidecar {
+        metadata,
+        compute_mode,
+        epsilon: data.epsilon,
+        files,
+    };
+    let path = file(".json");
+    fs::write(&path, serde_json::to_string_pretty(&sidecar)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned())
+}
@@ -0,0 +1,397 @@
+//! Procedural layouts: regular polygons, rings, lattices, seeded random clusters and symmetric
+//! copies of existing bodies. Everything is centered on the world origin.
+
+use crate::{palette::Palette, scene::Body};
+use anyhow::bail;
+use serde::{Deserialize, Serialize};
+use shader::MAX_GRAVITONS;
+use spirv_std::glam::{Vec2, Vec3};
+use std::f32::consts::TAU;
+
+/// How `Generator::Random` draws masses
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Distribution {
+    #[default]
+    Uniform,
+    /// uniform in the logarithm, for masses spanning orders of magnitude
+    LogUniform,
+}
+
+/// One step building a layout, in scene files tagged with `kind`
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize, clap::Subcommand)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Generator {
+    /// Bodies of equal mass on the corners of a regular polygon
+    Polygon {
+        #[arg(long, default_value_t = 5)]
+        sides: u32,
+        /// distance of the corners from the center
+        #[arg(long, default_value_t = 200.)]
+        radius: f32,
+        #[arg(long, default_value_t = 1.)]
+        mass: f32,
+        /// of the first corner counterclockwise from the x axis, in degrees
+        #[arg(long, default_value_t = 0.)]
+        #[serde(default)]
+        rotation: f32,
+    },
+    /// A polygon of bodies around a central one
+    Ring {
+        #[arg(long, default_value_t = 6)]
+        count: u32,
+        #[arg(long, default_value_t = 200.)]
+        radius: f32,
+        #[arg(long, default_value_t = 1.)]
+        mass: f32,
+        #[arg(long, default_value_t = 2.)]
+        central_mass: f32,
+    },
+    /// Bodies of equal mass on a square grid
+    Lattice {
+        #[arg(long, default_value_t = 3)]
+        columns: u32,
+        #[arg(long, default_value_t = 3)]
+        rows: u32,
+        #[arg(long, default_value_t = 100.)]
+        spacing: f32,
+        #[arg(long, default_value_t = 1.)]
+        mass: f32,
+    },
+    /// Bodies spread uniformly over a disk
+    Random {
+        #[arg(long, default_value_t = 6)]
+        count: u32,
+        #[arg(long, default_value_t = 200.)]
+        radius: f32,
+        /// the same seed gives the same layout
+        #[arg(long, default_value_t = 0)]
+        #[serde(default)]
+        seed: u64,
+        #[arg(long, value_enum, default_value_t)]
+        #[serde(default)]
+        distribution: Distribution,
+        #[arg(long, default_value_t = 0.5)]
+        min_mass: f32,
+        #[arg(long, default_value_t = 2.)]
+        max_mass: f32,
+    },
+    /// Add the mirror image of every body not on the mirror line through the center
+    Mirror {
+        /// of the mirror line from the x axis, in degrees
+        #[arg(long, default_value_t = 90.)]
+        #[serde(default)]
+        angle: f32,
+    },
+    /// Add rotated copies of every body, for `copies`-fold symmetry around the center
+    Rotate {
+        #[arg(long, default_value_t = 3)]
+        copies: u32,
+    },
+}
+impl Generator {
+    /// One of each kind with the command line defaults
+    pub fn defaults() -> [Self; 6] {
+        [
+            Self::Polygon {
+                sides: 5,
+                radius: 200.,
+                mass: 1.,
+                rotation: 0.,
+            },
+            Self::Ring {
+                count: 6,
+                radius: 200.,
+                mass: 1.,
+                central_mass: 2.,
+            },
+            Self::Lattice {
+                columns: 3,
+                rows: 3,
+                spacing: 100.,
+                mass: 1.,
+            },
+            Self::Random {
+                count: 6,
+                radius: 200.,
+                seed: 0,
+                distribution: Distribution::Uniform,
+                min_mass: 0.5,
+                max_mass: 2.,
+            },
+            Self::Mirror { angle: 90. },
+            Self::Rotate { copies: 3 },
+        ]
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Polygon { .. } => "polygon",
+            Self::Ring { .. } => "ring",
+            Self::Lattice { .. } => "lattice",
+            Self::Random { .. } => "random",
+            Self::Mirror { .. } => "mirror",
+            Self::Rotate { .. } => "rotate",
+        }
+    }
+
+    /// Positions and masses of the bodies to add to `bodies`, copies also take the source's color
+    fn generate(&self, bodies: &[Body]) -> Vec<(Vec2, f32, Option<Vec3>)> {
+        match *self {
+            Self::Polygon {
+                sides,
+                radius,
+                mass,
+                rotation,
+            } => polygon(sides, radius, rotation.to_radians())
+                .map(|position| (position, mass, None))
+                .collect(),
+            Self::Ring {
+                count,
+                radius,
+                mass,
+                central_mass,
+            } => std::iter::once((Vec2::ZERO, central_mass, None))
+                .chain(polygon(count, radius, 0.).map(|position| (position, mass, None)))
+                .collect(),
+            Self::Lattice {
+                columns,
+                rows,
+                spacing,
+                mass,
+            } => {
+                let offset = Vec2::new(columns as f32 - 1., rows as f32 - 1.) * 0.5;
+                (0..rows)
+                    .flat_map(|row| (0..columns).map(move |column| (column, row)))
+                    .map(|(column, row)| {
+                        let cell = Vec2::new(column as f32, row as f32) - offset;
+                        (cell * spacing, mass, None)
+                    })
+                    .collect()
+            }
+            Self::Random {
+                count,
+                radius,
+                seed,
+                distribution,
+                min_mass,
+                max_mass,
+            } => {
+                let mut random = SplitMix64(seed);
+                (0..count)
+                    .map(|_| {
+                        // the square root spreads the bodies evenly over the area
+                        let distance = radius * random.next_f32().sqrt();
+                        let position = Vec2::from_angle(TAU * random.next_f32()) * distance;
+                        let t = random.next_f32();
+                        let mass = match distribution {
+                            Distribution::Uniform => min_mass + (max_mass - min_mass) * t,
+                            Distribution::LogUniform => min_mass * (max_mass / min_mass).powf(t),
+                        };
+                        (position, mass, None)
+                    })
+                    .collect()
+            }
+            Self::Mirror { angle } => {
+                let axis = Vec2::from_angle(angle.to_radians());
+                copies(bodies, |position| 2. * position.dot(axis) * axis - position)
+            }
+            Self::Rotate { copies: count } => (1..count.max(1))
+                .flat_map(|i| {
+                    let rotation = Vec2::from_angle(TAU * i as f32 / count as f32);
+                    copies(bodies, |position| rotation.rotate(position))
+                })
+                .collect(),
+        }
+    }
+
+    /// Append the generated bodies, colored by `palette` as if it had been applied to all bodies.
+    /// Mirrored and rotated copies keep the color of the body they copy, to show the pairs.
+    /// Fails without changing `bodies` if they wouldn't fit into `Data`.
+    pub fn apply(&self, bodies: &mut Vec<Body>, palette: &Palette) -> anyhow::Result<()> {
+        let generated = self.generate(bodies);
+        if let Some((position, mass, _)) = generated
+            .iter()
+            .find(|(position, mass, _)| !position.is_finite() || !mass.is_finite())
+        {
+            bail!(
+                "The {} generator made a body at {position} with mass {mass}, check its settings",
+                self.name()
+            );
+        }
+        let count = bodies.len() + generated.len();
+        if count > MAX_GRAVITONS {
+            bail!(
+                "The {} generator would make {count} bodies, at most {MAX_GRAVITONS} are supported",
+                self.name()
+            );
+        }
+        let start = bodies.len();
+        bodies.extend(
+            generated
+                .into_iter()
+                .enumerate()
+                .map(|(i, (position, mass, color))| {
+                    let color = color.unwrap_or_else(|| {
+                        Vec3::from_array(palette.graviton_color(start + i, count))
+                    });
+                    Body::new(position, mass, color)
+                }),
+        );
+        Ok(())
+    }
+}
+
+/// `count` points evenly spaced on a circle, the first at `angle` radians
+fn polygon(count: u32, radius: f32, angle: f32) -> impl Iterator<Item = Vec2> {
+    (0..count).map(move |i| Vec2::from_angle(angle + TAU * i as f32 / count as f32) * radius)
+}
+
+/// `map`ped copies of the bodies that `map` moves, bodies it keeps in place would overlap
+fn copies(bodies: &[Body], map: impl Fn(Vec2) -> Vec2) -> Vec<(Vec2, f32, Option<Vec3>)> {
+    bodies
+        .iter()
+        .filter_map(|body| {
+            let position = map(body.position);
+            let tolerance = 1e-4 * body.position.length().max(1.);
+            (position.distance(body.position) > tolerance).then_some((
+                position,
+                body.mass,
+                Some(body.color),
+            ))
+        })
+        .collect()
+}
+
+/// SplitMix64 (Steele, Lea and Flood), small and good enough for layouts that have to be
+/// reproducible from a seed
+struct SplitMix64(u64);
+impl SplitMix64 {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `0..1`
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1 << 24) as f32
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spirv_std::glam::{vec2, vec3};
+    use std::f32::consts::PI;
+
+    fn generated(generator: Generator, mut bodies: Vec<Body>) -> Vec<Body> {
+        generator
+            .apply(&mut bodies, &Palette::builtin()[0])
+            .unwrap();
+        bodies
+    }
+
+    fn close(a: Vec2, b: Vec2) -> bool {
+        a.distance(b) < 1e-3
+    }
+
+    fn random(seed: u64) -> Vec<Body> {
+        generated(
+            Generator::Random {
+                count: 8,
+                radius: 200.,
+                seed,
+                distribution: Distribution::LogUniform,
+                min_mass: 0.1,
+                max_mass: 10.,
+            },
+            Vec::new(),
+        )
+    }
+
+    #[test]
+    fn random_is_reproducible() {
+        assert_eq!(random(7), random(7));
+        assert_ne!(random(7), random(8));
+        for body in random(7) {
+            assert!(body.position.length() <= 200. + 1e-3);
+            assert!((0.1..=10.).contains(&body.mass));
+        }
+    }
+
+    #[test]
+    fn polygon_spacing() {
+        let sides = 5;
+        let bodies = generated(
+            Generator::Polygon {
+                sides,
+                radius: 200.,
+                mass: 1.,
+                rotation: 0.,
+            },
+            Vec::new(),
+        );
+        assert_eq!(bodies.len(), sides as usize);
+        let side = 2. * 200. * (PI / sides as f32).sin();
+        for (i, body) in bodies.iter().enumerate() {
+            let next = &bodies[(i + 1) % bodies.len()];
+            assert!((body.position.length() - 200.).abs() < 1e-3);
+            assert!((body.position.distance(next.position) - side).abs() < 1e-3);
+        }
+        assert!(close(bodies[0].position, vec2(200., 0.)));
+    }
+
+    #[test]
+    fn mirror_copies_keep_mass_and_color() {
+        let red = Body::new(vec2(100., 50.), 2., vec3(1., 0., 0.));
+        // on the mirror line, a copy would overlap it
+        let on_line = Body::new(vec2(0., 30.), 1., vec3(0., 1., 0.));
+        let bodies = generated(Generator::Mirror { angle: 90. }, vec![red.clone(), on_line]);
+        assert_eq!(bodies.len(), 3);
+        let copy = &bodies[2];
+        assert!(close(copy.position, vec2(-100., 50.)));
+        assert_eq!((copy.mass, copy.color), (red.mass, red.color));
+    }
+
+    #[test]
+    fn rotate_copies_keep_mass_and_color() {
+        let body = Body::new(vec2(100., 0.), 2., vec3(0., 0., 1.));
+        let bodies = generated(Generator::Rotate { copies: 4 }, vec![body.clone()]);
+        let expected = [
+            vec2(100., 0.),
+            vec2(0., 100.),
+            vec2(-100., 0.),
+            vec2(0., -100.),
+        ];
+        assert_eq!(bodies.len(), expected.len());
+        for (copy, expected) in bodies.iter().zip(expected) {
+            assert!(close(copy.position, expected));
+            assert_eq!((copy.mass, copy.color), (body.mass, body.color));
+        }
+    }
+
+    #[test]
+    fn apply_rejects_without_changing_bodies() {
+        let palette = &Palette::builtin()[0];
+        let original = vec![Body::new(vec2(1., 2.), 1., Vec3::ONE)];
+        let mut bodies = original.clone();
+        let infinite = Generator::Polygon {
+            sides: 3,
+            radius: f32::INFINITY,
+            mass: 1.,
+            rotation: 0.,
+        };
+        assert!(infinite.apply(&mut bodies, palette).is_err());
+        let too_many = Generator::Lattice {
+            columns: 6,
+            rows: 6,
+            spacing: 10.,
+            mass: 1.,
+        };
+        assert!(too_many.apply(&mut bodies, palette).is_err());
+        assert_eq!(bodies, original);
+    }
+}
@@ -0,0 +1,174 @@
+//! Picking an adapter and creating the device, with readable errors for adapters the solvers
+//! can't run on
+
+use anyhow::{bail, Context};
+
+/// Which adapter to use, the best hardware one by default
+#[derive(Clone, Debug, Default)]
+pub struct AdapterChoice {
+    /// index into `adapters` or a case-insensitive part of the adapter name
+    pub adapter: Option<String>,
+    /// a software adapter such as lavapipe, llvmpipe or WARP
+    pub software: bool,
+}
+
+pub fn instance() -> wgpu::Instance {
+    wgpu::Instance::new(&wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    })
+}
+
+/// Every adapter of `instance`, in the order `AdapterChoice::adapter` indexes
+pub fn adapters(instance: &wgpu::Instance) -> Vec<wgpu::Adapter> {
+    instance.enumerate_adapters(wgpu::Backends::all())
+}
+
+/// Name, backend, type and the optional features the viewer uses
+pub fn describe(adapter: &wgpu::Adapter) -> String {
+    let info = adapter.get_info();
+    let mut description = format!("{} ({:?}, {:?})", info.name, info.backend, info.device_type);
+    for (feature, name) in [
+        (wgpu::Features::SHADER_F64, "f64"),
+        (wgpu::Features::TIMESTAMP_QUERY, "timestamp queries"),
+    ] {
+        if adapter.features().contains(feature) {
+            description += &format!(", {name}");
+        }
+    }
+    description
+}
+
+/// Fail if the compute shaders can't run on `adapter` or its textures can't be `texture_size`
+pub fn check(adapter: &wgpu::Adapter, texture_size: [u32; 2]) -> anyhow::Result<()> {
+    let name = adapter.get_info().name;
+    let downlevel = adapter.get_downlevel_capabilities();
+    if !downlevel
+        .flags
+        .contains(wgpu::DownlevelFlags::COMPUTE_SHADERS)
+    {
+        bail!("{name} doesn't support compute shaders");
+    }
+    for (format, texture) in [
+        (wgpu::TextureFormat::Rgba32Float, "results"),
+        (wgpu::TextureFormat::Rg32Float, "velocities"),
+        (wgpu::TextureFormat::R32Float, "boundary mask"),
+    ] {
+        let usages = adapter.get_texture_format_features(format).allowed_usages;
+        if !usages.contains(wgpu::TextureUsages::STORAGE_BINDING) {
+            bail!("{name} can't use {format:?} storage textures, needed for the {texture}");
+        }
+    }
+    // every compute entry point runs 16x16 invocations per workgroup
+    let limits = adapter.limits();
+    if limits.max_compute_workgroup_size_x < 16
+        || limits.max_compute_workgroup_size_y < 16
+        || limits.max_compute_invocations_per_workgroup < 256
+    {
+        bail!(
+            "{name} supports compute workgroups of at most {}x{} with {} invocations, the \
+             solvers need 16x16",
+            limits.max_compute_workgroup_size_x,
+            limits.max_compute_workgroup_size_y,
+            limits.max_compute_invocations_per_workgroup
+        );
+    }
+    let [width, height] = texture_size;
+    if width.max(height) > limits.max_texture_dimension_2d {
+        bail!(
+            "{name} supports textures of at most {0}x{0}, the results need {width}x{height}",
+            limits.max_texture_dimension_2d
+        );
+    }
+    Ok(())
+}
+
+/// The adapter `choice` asks for, which has to be able to present to `surface` if given and
+/// pass `check`. Without an explicit choice a software adapter is used if there is no usable
+/// hardware one.
+pub async fn select_adapter(
+    instance: &wgpu::Instance,
+    choice: &AdapterChoice,
+    surface: Option<&wgpu::Surface<'_>>,
+    texture_size: [u32; 2],
+) -> anyhow::Result<wgpu::Adapter> {
+    let adapter = if let Some(wanted) = &choice.adapter {
+        let adapters = adapters(instance);
+        let found = match wanted.parse::<usize>() {
+            Ok(index) => adapters.into_iter().nth(index),
+            Err(_) => {
+                let wanted = wanted.to_lowercase();
+                adapters
+                    .into_iter()
+                    .find(|adapter| adapter.get_info().name.to_lowercase().contains(&wanted))
+            }
+        };
+        let adapter = found.with_context(|| {
+            format!("No adapter matches '{wanted}', `gravity-basins adapters` lists them")
+        })?;
+        if surface.is_some_and(|surface| !adapter.is_surface_supported(surface)) {
+            bail!("{} can't present to the window", adapter.get_info().name);
+        }
+        adapter
+    } else if choice.software {
+        request_adapter(instance, surface, true)
+            .await
+            .context("No software adapter found, install lavapipe or another software driver")?
+    } else {
+        let hardware = request_adapter(instance, surface, false)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|adapter| check(&adapter, texture_size).map(|()| adapter));
+        match hardware {
+            Ok(adapter) => return Ok(adapter),
+            Err(error) => {
+                log::warn!(
+                    "No usable hardware adapter ({error:#}), falling back to a software adapter"
+                );
+                request_adapter(instance, surface, true)
+                    .await
+                    .with_context(|| {
+                        format!(
+                            "No usable GPU adapter found ({error:#}), install a Vulkan driver or \
+                             lavapipe for software rendering"
+                        )
+                    })?
+            }
+        }
+    };
+    check(&adapter, texture_size)?;
+    Ok(adapter)
+}
+
+async fn request_adapter(
+    instance: &wgpu::Instance,
+    surface: Option<&wgpu::Surface<'_>>,
+    force_fallback_adapter: bool,
+) -> Result<wgpu::Adapter, wgpu::RequestAdapterError> {
+    instance
+        .request_adapter(&wgpu::RequestAdapterOptions {
+            power_preference: wgpu::PowerPreference::HighPerformance,
+            compatible_surface: surface,
+            force_fallback_adapter,
+        })
+        .await
+}
+
+/// Device with the adapter's limits and the optional features it has: the native `f64` solver
+/// falls back to double-float without `SHADER_F64`, timings need `TIMESTAMP_QUERY`
+pub async fn request_device(
+    adapter: &wgpu::Adapter,
+) -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let required_features =
+        adapter.features() & (wgpu::Features::SHADER_F64 | wgpu::Features::TIMESTAMP_QUERY);
+    adapter
+        .request_device(&wgpu::DeviceDescriptor {
+            label: None,
+            required_features,
+            required_limits: adapter.limits(),
+            memory_hints: wgpu::MemoryHints::Performance,
+            trace: wgpu::Trace::Off,
+        })
+        .await
+        .with_context(|| format!("Failed to create a device on {}", adapter.get_info().name))
+}
@@ -0,0 +1,858 @@
+//! Side panel for editing gravitons and solver settings
+
+use crate::{overlay::Toggles, viewer::Layout};
+use egui::{Button, DragValue, Slider};
+use egui_wgpu::ScreenDescriptor;
+use gravity_basins::{
+    analysis::{BoxCounting, UncertaintyExponent, Wada},
+    camera::Camera,
+    generate::{Distribution, Generator},
+    palette::Palette,
+    scene::{Body, Integrator, Scene, Solver},
+    stats::BasinStats,
+    Precision,
+};
+use shader::{BoundaryMode, ColorMode, ComputeMode, Data, Parameter, MAX_GRAVITONS};
+use spirv_std::glam::{DVec2, Vec3};
+use std::{path::Path, time::Duration};
+use winit::{event::WindowEvent, window::Window};
+
+pub struct Gui {
+    context: egui::Context,
+    state: egui_winit::State,
+    renderer: egui_wgpu::Renderer,
+}
+
+/// Tessellated ui, ready to be drawn
+pub struct Frame {
+    paint_jobs: Vec<egui::ClippedPrimitive>,
+    textures_delta: egui::TexturesDelta,
+    screen: ScreenDescriptor,
+}
+
+impl Gui {
+    pub fn new(window: &Window, device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let context = egui::Context::default();
+        let state = egui_winit::State::new(
+            context.clone(),
+            egui::ViewportId::ROOT,
+            window,
+            Some(window.scale_factor() as f32),
+            None,
+            Some(device.limits().max_texture_dimension_2d as usize),
+        );
+        let renderer = egui_wgpu::Renderer::new(device, format, None, 1, false);
+        Self {
+            context,
+            state,
+            renderer,
+        }
+    }
+
+    /// Whether a widget is being dragged
+    pub fn is_using_pointer(&self) -> bool {
+        self.context.is_using_pointer()
+    }
+
+    /// Returns whether the ui consumed the event
+    pub fn on_window_event(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).consumed
+    }
+
+    pub fn run(&mut self, window: &Window, ui: impl FnMut(&egui::Context)) -> Frame {
+        let input = self.state.take_egui_input(window);
+        let output = self.context.run(input, ui);
+        self.state
+            .handle_platform_output(window, output.platform_output);
+
+        let size = window.inner_size();
+        Frame {
+            paint_jobs: self
+                .context
+                .tessellate(output.shapes, output.pixels_per_point),
+            textures_delta: output.textures_delta,
+            screen: ScreenDescriptor {
+                size_in_pixels: [size.width, size.height],
+                pixels_per_point: output.pixels_per_point,
+            },
+        }
+    }
+
+    /// Upload textures and buffers, the returned command buffers have to be submitted before `encoder`
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        encoder: &mut wgpu::CommandEncoder,
+        frame: &Frame,
+    ) -> Vec<wgpu::CommandBuffer> {
+        for (id, delta) in &frame.textures_delta.set {
+            self.renderer.update_texture(device, queue, *id, delta);
+        }
+        self.renderer
+            .update_buffers(device, queue, encoder, &frame.paint_jobs, &frame.screen)
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'static>, frame: &Frame) {
+        self.renderer
+            .render(render_pass, &frame.paint_jobs, &frame.screen);
+    }
+
+    /// Free textures egui no longer needs, call after submitting
+    pub fn finish(&mut self, frame: Frame) {
+        for id in &frame.textures_delta.free {
+            self.renderer.free_texture(id);
+        }
+    }
+}
+
+/// Readouts shown at the bottom of the panel, the timings also in the corner of the window
+pub struct Stats {
+    pub frame_time: Duration,
+    /// GPU time of the last timed solve of the main view
+    pub compute_time: Option<Duration>,
+    pub width: u32,
+    pub height: u32,
+    pub scale_bar_length: Option<f64>,
+}
+
+impl Stats {
+    fn timings(&self, ui: &mut egui::Ui) {
+        let milliseconds = |time: Duration| format!("{:.1} ms", time.as_secs_f64() * 1000.);
+        ui.label(format!("frame time: {}", milliseconds(self.frame_time)));
+        let compute_time = self.compute_time.map_or("n/a".to_string(), milliseconds);
+        ui.label(format!("compute time: {compute_time}"));
+    }
+}
+
+/// Everything the panel edits
+pub struct Panel<'a> {
+    /// bodies and solver settings
+    pub scene: &'a mut Scene,
+    /// view and coloring settings, the bodies and solver settings in it are rebuilt from `scene`
+    pub data: &'a mut Data,
+    pub camera: &'a mut Camera,
+    pub precision: &'a mut Precision,
+    pub f64_available: bool,
+    /// size of one view in pixels, a split layout divides the window between several
+    pub view_size: DVec2,
+    pub overlay_toggles: &'a mut Toggles,
+    pub selected: &'a mut Option<usize>,
+    pub generator: &'a mut Generator,
+    pub layout: &'a mut Layout,
+    /// solver settings of the comparison views
+    pub comparisons: &'a mut Vec<Solver>,
+    pub palettes: &'a mut Vec<Palette>,
+    pub graviton_palette: &'a mut usize,
+    pub shading_palette: &'a mut usize,
+    pub palette_path: &'a mut String,
+    pub basin_stats: Option<&'a BasinStats>,
+    pub auto_stats: &'a mut bool,
+    /// set when the statistics should be computed
+    pub compute_stats: bool,
+    /// set when the statistics should be saved as CSV
+    pub export_stats: bool,
+    pub box_counting: Option<&'a BoxCounting>,
+    /// set when the box-counting dimension should be estimated
+    pub compute_dimension: bool,
+    pub uncertainty_exponent: Option<&'a UncertaintyExponent>,
+    /// set when the uncertainty exponent should be fitted
+    pub compute_uncertainty_exponent: bool,
+    pub wada: Option<&'a Wada>,
+    /// set when the Wada check should run
+    pub compute_wada: bool,
+    /// set when the raw results should be exported
+    pub export_results: bool,
+    /// set when the basins should be saved as SVG
+    pub export_svg: bool,
+    /// set to the file the colored view should be saved to at full precision
+    pub export_image: Option<&'static str>,
+    /// set when parameter space should be entered or left
+    pub toggle_parameter_space: bool,
+    pub stats: Stats,
+}
+
+impl Panel<'_> {
+    pub fn show(&mut self, context: &egui::Context) {
+        egui::SidePanel::left("panel")
+            .default_width(320.)
+            .show(context, |ui| {
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    ui.heading("Gravitons");
+                    self.gravitons(ui);
+                    ui.separator();
+                    ui.heading("Generate");
+                    self.generate(ui);
+                    ui.separator();
+                    ui.heading("Colors");
+                    self.colors(ui);
+                    ui.separator();
+                    ui.heading("Boundaries");
+                    self.boundaries(ui);
+                    ui.separator();
+                    ui.heading("Solver");
+                    self.solver(ui);
+                    ui.separator();
+                    ui.heading("Compare");
+                    self.compare(ui);
+                    ui.separator();
+                    ui.heading("Parameter space");
+                    self.parameter_space(ui);
+                    ui.separator();
+                    ui.heading("Camera");
+                    self.camera(ui);
+                    ui.separator();
+                    ui.heading("Overlay");
+                    self.overlay(ui);
+                    ui.separator();
+                    ui.heading("Basins");
+                    self.basins(ui);
+                    ui.separator();
+                    ui.heading("Statistics");
+                    self.stats(ui);
+                });
+            });
+        let basin_stats = self
+            .basin_stats
+            .filter(|_| self.overlay_toggles.basin_stats);
+        if self.overlay_toggles.timings || basin_stats.is_some() {
+            egui::Area::new("readouts".into())
+                .anchor(egui::Align2::RIGHT_TOP, [-8., 8.])
+                .interactable(false)
+                .show(context, |ui| {
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        if self.overlay_toggles.timings {
+                            self.stats.timings(ui);
+                        }
+                        if let Some(stats) = basin_stats {
+                            basin_readout(ui, stats);
+                        }
+                    });
+                });
+        }
+    }
+
+    fn gravitons(&mut self, ui: &mut egui::Ui) {
+        // dragging by a pixel moves by a pixel
+        let speed = self.camera.scale;
+        let mut remove = None;
+        egui::Grid::new("gravitons").striped(true).show(ui, |ui| {
+            for label in ["", "x", "y", "mass", "color", ""] {
+                ui.label(label);
+            }
+            ui.end_row();
+
+            for (i, body) in self.scene.bodies.iter_mut().enumerate() {
+                let label = body.name.clone().unwrap_or_else(|| i.to_string());
+                if ui
+                    .selectable_label(*self.selected == Some(i), label)
+                    .clicked()
+                {
+                    *self.selected = Some(i);
+                }
+                ui.add(DragValue::new(&mut body.position.x).speed(speed));
+                ui.add(DragValue::new(&mut body.position.y).speed(speed));
+                ui.add(
+                    DragValue::new(&mut body.mass)
+                        .speed(0.01)
+                        .range(0.0..=f32::MAX),
+                );
+                let mut color = body.color.to_array();
+                if ui.color_edit_button_rgb(&mut color).changed() {
+                    body.color = Vec3::from_array(color);
+                }
+                if ui.button("Remove").clicked() {
+                    remove = Some(i);
+                }
+                ui.end_row();
+            }
+        });
+
+        if let Some(index) = remove {
+            self.scene.bodies.remove(index);
+            *self.selected = match *self.selected {
+                Some(selected) if selected == index => None,
+                Some(selected) if selected > index => Some(selected - 1),
+                selected => selected,
+            };
+        }
+        let room_left = self.scene.bodies.len() < MAX_GRAVITONS;
+        if ui.add_enabled(room_left, Button::new("Add")).clicked() {
+            let center = self.camera.center.as_vec2();
+            self.scene.bodies.push(Body::new(center, 1., Vec3::ONE));
+            *self.selected = Some(self.scene.bodies.len() - 1);
+        }
+    }
+
+    fn generate(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("generator")
+            .selected_text(self.generator.name())
+            .show_ui(ui, |ui| {
+                for generator in Generator::defaults() {
+                    let selected = generator.name() == self.generator.name();
+                    if ui.selectable_label(selected, generator.name()).clicked() && !selected {
+                        *self.generator = generator;
+                    }
+                }
+            });
+        let count_range = 0..=MAX_GRAVITONS as u32;
+        match self.generator {
+            Generator::Polygon {
+                sides,
+                radius,
+                mass: body_mass,
+                rotation,
+            } => {
+                labeled(ui, "sides", DragValue::new(sides).range(count_range));
+                labeled(ui, "radius", positive(radius, 1.));
+                labeled(ui, "mass", positive(body_mass, 0.01));
+                labeled(ui, "rotation (degrees)", DragValue::new(rotation));
+            }
+            Generator::Ring {
+                count,
+                radius,
+                mass: body_mass,
+                central_mass,
+            } => {
+                labeled(ui, "count", DragValue::new(count).range(count_range));
+                labeled(ui, "radius", positive(radius, 1.));
+                labeled(ui, "mass", positive(body_mass, 0.01));
+                labeled(ui, "central mass", positive(central_mass, 0.01));
+            }
+            Generator::Lattice {
+                columns,
+                rows,
+                spacing,
+                mass: body_mass,
+            } => {
+                labeled(
+                    ui,
+                    "columns",
+                    DragValue::new(columns).range(count_range.clone()),
+                );
+                labeled(ui, "rows", DragValue::new(rows).range(count_range));
+                labeled(ui, "spacing", positive(spacing, 1.));
+                labeled(ui, "mass", positive(body_mass, 0.01));
+            }
+            Generator::Random {
+                count,
+                radius,
+                seed,
+                distribution,
+                min_mass,
+                max_mass,
+            } => {
+                labeled(ui, "count", DragValue::new(count).range(count_range));
+                labeled(ui, "radius", positive(radius, 1.));
+                labeled(ui, "seed", DragValue::new(seed));
+                egui::ComboBox::from_label("masses")
+                    .selected_text(format!("{distribution:?}"))
+                    .show_ui(ui, |ui| {
+                        ui.selectable_value(distribution, Distribution::Uniform, "Uniform");
+                        ui.selectable_value(distribution, Distribution::LogUniform, "LogUniform");
+                    });
+                labeled(ui, "min mass", positive(min_mass, 0.01));
+                labeled(ui, "max mass", positive(max_mass, 0.01));
+            }
+            Generator::Mirror { angle } => {
+                labeled(ui, "mirror angle (degrees)", DragValue::new(angle));
+            }
+            Generator::Rotate { copies } => {
+                labeled(
+                    ui,
+                    "copies",
+                    DragValue::new(copies).range(1..=MAX_GRAVITONS as u32),
+                );
+            }
+        }
+        ui.horizontal(|ui| {
+            if ui.button("Add").clicked() {
+                self.run_generator(false);
+            }
+            if ui.button("Replace").clicked() {
+                self.run_generator(true);
+            }
+        })
+        .response
+        .on_hover_text("Add to the gravitons or replace them, centered on the world origin");
+    }
+
+    /// Apply the generator to the gravitons, colored with the graviton palette
+    fn run_generator(&mut self, replace: bool) {
+        let mut bodies = if replace {
+            Vec::new()
+        } else {
+            self.scene.bodies.clone()
+        };
+        let palette = &self.palettes[*self.graviton_palette];
+        if let Err(error) = self.generator.apply(&mut bodies, palette) {
+            log::error!("{error:#}");
+            return;
+        }
+        self.scene.bodies = bodies;
+        *self.selected = None;
+        if replace {
+            // the generated layout is around the origin, which may well be off screen
+            *self.camera = self.scene.fit_camera(self.view_size);
+        }
+    }
+
+    fn colors(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            palette_combo(ui, "graviton palette", self.palettes, self.graviton_palette);
+            if ui.button("Apply").clicked() {
+                self.palettes[*self.graviton_palette].apply(&mut self.scene.bodies);
+            }
+        });
+        egui::ComboBox::from_label("color mode")
+            .selected_text(color_mode_name(self.data.color_mode))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    ColorMode::BASIN,
+                    ColorMode::SHADED,
+                    ColorMode::CAPTURE_TIME,
+                    ColorMode::UNCERTAINTY,
+                    ColorMode::FTLE,
+                ] {
+                    ui.selectable_value(&mut self.data.color_mode, mode, color_mode_name(mode));
+                }
+            });
+        palette_combo(ui, "shading palette", self.palettes, self.shading_palette);
+        ui.add(
+            Slider::new(&mut self.data.time_scale, 0.1..=1000.)
+                .logarithmic(true)
+                .text("time scale"),
+        );
+        ui.add(
+            Slider::new(&mut self.data.ftle_scale, 0.01..=100.)
+                .logarithmic(true)
+                .text("FTLE scale"),
+        );
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(self.palette_path);
+            if ui.button("Import").clicked() {
+                match Palette::load(Path::new(self.palette_path.trim())) {
+                    Ok(palette) => {
+                        log::info!("Imported palette {}", palette.name);
+                        self.palettes.push(palette);
+                    }
+                    Err(error) => log::error!("{error:#}"),
+                }
+            }
+        })
+        .response
+        .on_hover_text(".gpl, .map or .csv palette file");
+    }
+
+    fn boundaries(&mut self, ui: &mut egui::Ui) {
+        let data = &mut *self.data;
+        egui::ComboBox::from_label("boundaries")
+            .selected_text(boundary_mode_name(data.boundary_mode))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    BoundaryMode::OFF,
+                    BoundaryMode::HIGHLIGHT,
+                    BoundaryMode::ONLY,
+                    BoundaryMode::WADA,
+                ] {
+                    ui.selectable_value(&mut data.boundary_mode, mode, boundary_mode_name(mode));
+                }
+            });
+        ui.horizontal(|ui| {
+            let mut color = [
+                data.boundary_color_r,
+                data.boundary_color_g,
+                data.boundary_color_b,
+            ];
+            if ui.color_edit_button_rgb(&mut color).changed() {
+                [
+                    data.boundary_color_r,
+                    data.boundary_color_g,
+                    data.boundary_color_b,
+                ] = color;
+            }
+            ui.label("color");
+        });
+        ui.add(Slider::new(&mut data.boundary_thickness, 1..=16).text("thickness"));
+    }
+
+    fn solver(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("precision")
+            .selected_text(format!("{:?}", self.precision))
+            .show_ui(ui, |ui| {
+                let precision = &mut *self.precision;
+                ui.selectable_value(precision, Precision::F32, "F32");
+                ui.selectable_value(precision, Precision::DoubleFloat, "DoubleFloat");
+                if self.f64_available {
+                    ui.selectable_value(precision, Precision::F64, "F64");
+                }
+            });
+        integrator_combo(ui, "integrator", &mut self.scene.solver.integrator);
+        let compute_mode = self.data.compute_mode;
+        egui::ComboBox::from_label("compute")
+            .selected_text(compute_mode_name(compute_mode))
+            .show_ui(ui, |ui| {
+                for mode in [
+                    ComputeMode::BASINS,
+                    ComputeMode::UNCERTAINTY,
+                    ComputeMode::FTLE,
+                ] {
+                    ui.selectable_value(&mut self.data.compute_mode, mode, compute_mode_name(mode));
+                }
+            });
+        // show what was just computed
+        match self.data.compute_mode {
+            mode if mode == compute_mode => {}
+            ComputeMode::UNCERTAINTY => self.data.color_mode = ColorMode::UNCERTAINTY,
+            ComputeMode::FTLE => self.data.color_mode = ColorMode::FTLE,
+            _ if matches!(
+                self.data.color_mode,
+                ColorMode::UNCERTAINTY | ColorMode::FTLE
+            ) =>
+            {
+                self.data.color_mode = ColorMode::BASIN
+            }
+            _ => {}
+        }
+        if self.data.compute_mode != ComputeMode::BASINS {
+            ui.add(
+                Slider::new(&mut self.data.epsilon, 1e-3..=4.)
+                    .logarithmic(true)
+                    .text("epsilon (pixels)"),
+            );
+        }
+        let solver = &mut self.scene.solver;
+        ui.add(
+            Slider::new(&mut solver.max_steps, 10..=100_000)
+                .logarithmic(true)
+                .text("max steps"),
+        );
+        ui.add(
+            Slider::new(&mut solver.capture_radius, 0.1..=1000.)
+                .logarithmic(true)
+                .text("capture radius"),
+        );
+        ui.add(
+            Slider::new(&mut solver.min_dt, 1e-5..=solver.max_dt)
+                .logarithmic(true)
+                .text("min dt"),
+        );
+        ui.add(
+            Slider::new(&mut solver.max_dt, solver.min_dt..=1.)
+                .logarithmic(true)
+                .text("max dt"),
+        );
+    }
+
+    fn compare(&mut self, ui: &mut egui::Ui) {
+        egui::ComboBox::from_label("layout")
+            .selected_text(format!("{:?}", self.layout))
+            .show_ui(ui, |ui| {
+                for layout in [Layout::Single, Layout::Split, Layout::Difference] {
+                    ui.selectable_value(self.layout, layout, format!("{layout:?}"));
+                }
+            })
+            .response
+            .on_hover_text("difference highlights where the first comparison finds another basin");
+        let mut remove = None;
+        for (i, solver) in self.comparisons.iter_mut().enumerate() {
+            ui.push_id(i, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label(format!("view {}", i + 2));
+                    integrator_combo(ui, "integrator", &mut solver.integrator);
+                    if ui.button("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                });
+                ui.add(
+                    Slider::new(&mut solver.max_steps, 10..=100_000)
+                        .logarithmic(true)
+                        .text("max steps"),
+                );
+                ui.add(
+                    Slider::new(&mut solver.min_dt, 1e-5..=solver.max_dt)
+                        .logarithmic(true)
+                        .text("min dt"),
+                );
+                ui.add(
+                    Slider::new(&mut solver.max_dt, solver.min_dt..=1.)
+                        .logarithmic(true)
+                        .text("max dt"),
+                );
+            });
+        }
+        if let Some(index) = remove {
+            self.comparisons.remove(index);
+        }
+        if ui.button("Add view").clicked() {
+            self.comparisons.push(self.scene.solver);
+        }
+    }
+
+    fn parameter_space(&mut self, ui: &mut egui::Ui) {
+        let count = self.scene.bodies.len() as u32;
+        if count == 0 {
+            ui.label("needs a graviton");
+            return;
+        }
+        let data = &mut *self.data;
+        parameter_axis(
+            ui,
+            "x axis",
+            &mut data.parameter_x,
+            &mut data.parameter_x_graviton,
+            count,
+        );
+        parameter_axis(
+            ui,
+            "y axis",
+            &mut data.parameter_y,
+            &mut data.parameter_y_graviton,
+            count,
+        );
+        ui.horizontal(|ui| {
+            let speed = self.camera.scale;
+            ui.add(DragValue::new(&mut data.start_x).speed(speed));
+            ui.add(DragValue::new(&mut data.start_y).speed(speed));
+            ui.label("start");
+        })
+        .response
+        .on_hover_text("where the particle is released from");
+        if data.parameter_space != 0 {
+            ui.add(
+                Slider::new(&mut data.parameter_y_scale, 1e-6..=1e6)
+                    .logarithmic(true)
+                    .text("y per x unit"),
+            );
+            self.toggle_parameter_space |= ui
+                .button("Back to position space")
+                .on_hover_text("or click a pixel to use its parameters")
+                .clicked();
+        } else {
+            self.toggle_parameter_space |= ui.button("Explore").clicked();
+        }
+    }
+
+    fn camera(&mut self, ui: &mut egui::Ui) {
+        let speed = self.camera.scale;
+        ui.horizontal(|ui| {
+            ui.label("center");
+            ui.add(DragValue::new(&mut self.camera.center.x).speed(speed));
+            ui.add(DragValue::new(&mut self.camera.center.y).speed(speed));
+        });
+        ui.horizontal(|ui| {
+            ui.label("units per pixel");
+            ui.add(
+                DragValue::new(&mut self.camera.scale)
+                    .speed(self.camera.scale * 0.01)
+                    .range(f64::MIN_POSITIVE..=f64::MAX),
+            );
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Zoom in").clicked() {
+                self.camera.scale /= 2.;
+            }
+            if ui.button("Zoom out").clicked() {
+                self.camera.scale *= 2.;
+            }
+            if ui.button("Reset").clicked() {
+                *self.camera = Camera::new(self.view_size * 0.5);
+            }
+        });
+    }
+
+    fn overlay(&mut self, ui: &mut egui::Ui) {
+        let toggles = &mut *self.overlay_toggles;
+        ui.checkbox(&mut toggles.gravitons, "gravitons");
+        ui.checkbox(&mut toggles.capture_radii, "capture radii");
+        ui.checkbox(&mut toggles.selection, "selection");
+        ui.checkbox(&mut toggles.scale_bar, "scale bar");
+        ui.checkbox(&mut toggles.trajectory, "trajectory");
+        ui.checkbox(&mut toggles.timings, "timings");
+        ui.checkbox(&mut toggles.basin_stats, "basin statistics");
+    }
+
+    fn basins(&mut self, ui: &mut egui::Ui) {
+        ui.horizontal(|ui| {
+            ui.checkbox(self.auto_stats, "auto");
+            self.compute_stats |= ui.button("Compute").clicked();
+            self.export_stats |= ui.button("Export CSV").clicked();
+            self.export_results |= ui.button("Export arrays").clicked();
+            self.export_svg |= ui.button("Export SVG").clicked();
+        });
+        ui.horizontal(|ui| {
+            if ui.button("Export EXR").clicked() {
+                self.export_image = Some("basins.exr");
+            }
+            if ui.button("Export 16-bit PNG").clicked() {
+                self.export_image = Some("basins.png");
+            }
+        });
+        ui.horizontal(|ui| {
+            self.compute_dimension |= ui.button("Dimension").clicked();
+            if let Some(box_counting) = self.box_counting {
+                ui.label(box_counting.summary());
+            }
+        });
+        ui.horizontal(|ui| {
+            self.compute_uncertainty_exponent |= ui.button("Uncertainty exponent").clicked();
+            if let Some(exponent) = self.uncertainty_exponent {
+                ui.label(exponent.summary());
+            }
+        });
+        ui.horizontal(|ui| {
+            self.compute_wada |= ui.button("Wada check").clicked();
+            if let Some(wada) = self.wada {
+                ui.label(wada.summary());
+            }
+        });
+        let Some(stats) = self.basin_stats else {
+            return;
+        };
+        egui::Grid::new("basins").striped(true).show(ui, |ui| {
+            for label in ["", "pixels", "share", "mean t", "median t"] {
+                ui.label(label);
+            }
+            ui.end_row();
+
+            for (i, basin) in stats.basins.iter().enumerate() {
+                ui.label(i.to_string());
+                ui.label(basin.pixels.to_string());
+                ui.label(format!("{:.2}%", stats.fraction(basin.pixels) * 100.));
+                if basin.pixels > 0 {
+                    ui.label(format!("{:.3}", basin.mean_time));
+                    ui.label(format!("{:.3}", basin.median_time));
+                }
+                ui.end_row();
+            }
+            ui.label("none");
+            ui.label(stats.uncaptured.to_string());
+            ui.label(format!("{:.2}%", stats.fraction(stats.uncaptured) * 100.));
+            ui.end_row();
+        });
+    }
+
+    fn stats(&self, ui: &mut egui::Ui) {
+        let stats = &self.stats;
+        stats.timings(ui);
+        ui.label(format!("resolution: {}x{}", stats.width, stats.height));
+        ui.label(format!("gravitons: {}", self.scene.bodies.len()));
+        if let Some(length) = stats.scale_bar_length {
+            ui.label(format!("scale bar: {length}"));
+        }
+    }
+}
+
+/// One line per basin with pixels, for the corner of the window
+fn basin_readout(ui: &mut egui::Ui, stats: &BasinStats) {
+    for (i, basin) in stats.basins.iter().enumerate() {
+        if basin.pixels > 0 {
+            ui.label(format!(
+                "basin {i}: {:.1}%, mean t {:.3}",
+                stats.fraction(basin.pixels) * 100.,
+                basin.mean_time
+            ));
+        }
+    }
+    if stats.uncaptured > 0 {
+        ui.label(format!(
+            "uncaptured: {:.1}%",
+            stats.fraction(stats.uncaptured) * 100.
+        ));
+    }
+}
+
+fn color_mode_name(mode: u32) -> &'static str {
+    match mode {
+        ColorMode::SHADED => "shaded",
+        ColorMode::CAPTURE_TIME => "capture time",
+        ColorMode::UNCERTAINTY => "uncertainty",
+        ColorMode::FTLE => "FTLE",
+        _ => "basin",
+    }
+}
+
+fn compute_mode_name(mode: u32) -> &'static str {
+    match mode {
+        ComputeMode::UNCERTAINTY => "uncertainty",
+        ComputeMode::FTLE => "FTLE",
+        _ => "basins",
+    }
+}
+
+fn boundary_mode_name(mode: u32) -> &'static str {
+    match mode {
+        BoundaryMode::HIGHLIGHT => "highlight",
+        BoundaryMode::ONLY => "only",
+        BoundaryMode::WADA => "Wada points",
+        _ => "off",
+    }
+}
+
+fn integrator_combo(ui: &mut egui::Ui, label: &str, integrator: &mut Integrator) {
+    egui::ComboBox::from_label(label)
+        .selected_text(format!("{integrator:?}"))
+        .show_ui(ui, |ui| {
+            for value in Integrator::ALL {
+                ui.selectable_value(integrator, value, format!("{value:?}"));
+            }
+        });
+}
+
+fn parameter_name(parameter: u32) -> &'static str {
+    match parameter {
+        Parameter::POSITION_Y => "y",
+        Parameter::MASS => "mass",
+        _ => "x",
+    }
+}
+
+/// Which parameter of which graviton an axis of parameter space varies
+fn parameter_axis(
+    ui: &mut egui::Ui,
+    label: &str,
+    parameter: &mut u32,
+    graviton: &mut u32,
+    count: u32,
+) {
+    ui.horizontal(|ui| {
+        ui.add(
+            DragValue::new(graviton)
+                .range(0..=count - 1)
+                .prefix("graviton "),
+        );
+        egui::ComboBox::from_label(label)
+            .selected_text(parameter_name(*parameter))
+            .show_ui(ui, |ui| {
+                for value in [
+                    Parameter::POSITION_X,
+                    Parameter::POSITION_Y,
+                    Parameter::MASS,
+                ] {
+                    ui.selectable_value(parameter, value, parameter_name(value));
+                }
+            });
+    });
+}
+
+fn palette_combo(ui: &mut egui::Ui, label: &str, palettes: &[Palette], selected: &mut usize) {
+    egui::ComboBox::from_label(label)
+        .selected_text(&palettes[*selected].name)
+        .show_ui(ui, |ui| {
+            for (i, palette) in palettes.iter().enumerate() {
+                ui.selectable_value(selected, i, &palette.name);
+            }
+        });
+}
+
+/// Drag value for lengths and masses
+fn positive(value: &mut f32, speed: f64) -> DragValue<'_> {
+    DragValue::new(value).speed(speed).range(0.0..=f32::MAX)
+}
+
+fn labeled(ui: &mut egui::Ui, label: &str, value: DragValue) {
+    ui.horizontal(|ui| {
+        ui.add(value);
+        ui.label(label);
+    });
+}
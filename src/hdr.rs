@@ -0,0 +1,163 @@
+//! Full precision image export: OpenEXR with the raw result channels, or 16-bit PNG
+
+use crate::palette::{linear_to_srgb, Palette};
+use anyhow::Context;
+use shader::{BoundaryMode, ColorMode, ComputeMode, Data};
+use std::{fs::File, io::BufWriter, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ImageFormat {
+    /// linear color plus label, steps, time and the fourth result channel, all `f32`
+    Exr,
+    /// sRGB color only, 16 bits per channel
+    Png16,
+}
+impl ImageFormat {
+    /// Format for the extension of `path`
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        let extension = path.extension().and_then(|extension| extension.to_str());
+        match extension.map(str::to_ascii_lowercase).as_deref() {
+            Some("exr") => Ok(Self::Exr),
+            Some("png") => Ok(Self::Png16),
+            _ => anyhow::bail!(
+                "Unknown image format for {}, use .exr or .png",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// CPU version of `fs_main`, in linear RGB. `boundaries` is the one pixel mask of
+/// `cs_boundary` (or the Wada marks), the boundary thickness isn't applied.
+pub fn colorize(
+    results: &[[f32; 4]],
+    boundaries: Option<&[f32]>,
+    data: &Data,
+    palette: &Palette,
+) -> Vec<[f32; 3]> {
+    let boundary_color = [
+        data.boundary_color_r,
+        data.boundary_color_g,
+        data.boundary_color_b,
+    ];
+    results
+        .iter()
+        .enumerate()
+        .map(|(i, &[label, _, time, w])| {
+            let time = (time / data.time_scale).clamp(0., 1.);
+            let boundary = match boundaries {
+                Some(mask) if data.boundary_mode != BoundaryMode::OFF => mask[i],
+                _ => 0.,
+            };
+            if data.boundary_mode == BoundaryMode::WADA && boundary > 1.5 {
+                boundary_color
+            } else if data.boundary_mode == BoundaryMode::WADA && boundary > 0.5 {
+                boundary_color.map(|channel| channel * 0.35)
+            } else if boundary > 0.5 {
+                boundary_color
+            } else if data.boundary_mode == BoundaryMode::ONLY {
+                [0.; 3]
+            } else if data.color_mode == ColorMode::UNCERTAINTY && w > 0.5 {
+                boundary_color
+            } else if data.color_mode == ColorMode::CAPTURE_TIME {
+                palette.sample(time)
+            } else if data.color_mode == ColorMode::FTLE {
+                palette.sample(w / data.ftle_scale)
+            } else if label < 0. {
+                [0.; 3]
+            } else {
+                let graviton = data.gravitons[label as usize];
+                let brightness = match data.color_mode {
+                    _ if data.boundary_mode == BoundaryMode::DIFFERENCE => 0.3,
+                    ColorMode::SHADED => 1. - 0.8 * time,
+                    ColorMode::UNCERTAINTY => 0.3,
+                    _ => 1.,
+                };
+                [graviton.color_r, graviton.color_g, graviton.color_b]
+                    .map(|channel| channel * brightness)
+            }
+        })
+        .collect()
+}
+
+/// Write `colors` and the raw `results` it was colored from, both row-major
+pub fn write_exr(
+    path: &Path,
+    width: u32,
+    height: u32,
+    colors: &[[f32; 3]],
+    results: &[[f32; 4]],
+    data: &Data,
+) -> anyhow::Result<()> {
+    use exr::prelude::*;
+
+    let color = |i: usize| FlatSamples::F32(colors.iter().map(|color| color[i]).collect());
+    let result = |i: usize| FlatSamples::F32(results.iter().map(|texel| texel[i]).collect());
+    let extra = match data.compute_mode {
+        ComputeMode::UNCERTAINTY => "uncertain",
+        ComputeMode::FTLE => "ftle",
+        _ => "speed",
+    };
+    let channels = AnyChannels::sort(SmallVec::from_vec(vec![
+        AnyChannel::new("R", color(0)),
+        AnyChannel::new("G", color(1)),
+        AnyChannel::new("B", color(2)),
+        AnyChannel::new("label", result(0)),
+        AnyChannel::new("steps", result(1)),
+        AnyChannel::new("time", result(2)),
+        AnyChannel::new(extra, result(3)),
+    ]));
+    let layer = Layer::new(
+        (width as usize, height as usize),
+        LayerAttributes::named("basins"),
+        Encoding::FAST_LOSSLESS,
+        channels,
+    );
+    Image::from_layer(layer)
+        .write()
+        .to_file(path)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Write `colors` (linear, row-major) as 16-bit sRGB
+pub fn write_png16(
+    path: &Path,
+    width: u32,
+    height: u32,
+    colors: &[[f32; 3]],
+) -> anyhow::Result<()> {
+    let file =
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?;
+    let mut encoder = png::Encoder::new(BufWriter::new(file), width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Sixteen);
+    encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+    let mut writer = encoder.write_header()?;
+    // PNG samples are big endian
+    let bytes: Vec<u8> = colors
+        .iter()
+        .flatten()
+        .flat_map(|&channel| {
+            let value = (linear_to_srgb(channel).clamp(0., 1.) * 65535.).round() as u16;
+            value.to_be_bytes()
+        })
+        .collect();
+    writer.write_image_data(&bytes)?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Write `colors` from `colorize`, EXR also gets the raw `results`
+pub fn save(
+    path: &Path,
+    width: u32,
+    height: u32,
+    colors: &[[f32; 3]],
+    results: &[[f32; 4]],
+    data: &Data,
+) -> anyhow::Result<()> {
+    match ImageFormat::from_path(path)? {
+        ImageFormat::Exr => write_exr(path, width, height, colors, results, data),
+        ImageFormat::Png16 => write_png16(path, width, height, colors),
+    }
+}
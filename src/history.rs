@@ -0,0 +1,388 @@
+//! Undo/redo for scene edits. Front-ends only snapshot the scene before an edit, `History::diff`
+//! turns the snapshot into the edits that are recorded
+
+use gravity_basins::{camera::Camera, scene::Body};
+use spirv_std::glam::{Vec2, Vec3};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Edit {
+    Add {
+        index: usize,
+        body: Body,
+    },
+    Remove {
+        index: usize,
+        body: Body,
+    },
+    Move {
+        index: usize,
+        from: Vec2,
+        to: Vec2,
+    },
+    Mass {
+        index: usize,
+        from: f32,
+        to: f32,
+    },
+    Color {
+        index: usize,
+        from: Vec3,
+        to: Vec3,
+    },
+    /// the whole body, for edits of its name or id
+    Replace {
+        index: usize,
+        from: Body,
+        to: Body,
+    },
+    Camera {
+        from: Camera,
+        to: Camera,
+    },
+}
+impl Edit {
+    fn apply(&self, bodies: &mut Vec<Body>, camera: &mut Camera) {
+        match self {
+            Edit::Add { index, body } => bodies.insert(*index, body.clone()),
+            Edit::Remove { index, .. } => {
+                bodies.remove(*index);
+            }
+            Edit::Move { index, to, .. } => bodies[*index].position = *to,
+            Edit::Mass { index, to, .. } => bodies[*index].mass = *to,
+            Edit::Color { index, to, .. } => bodies[*index].color = *to,
+            Edit::Replace { index, to, .. } => bodies[*index] = to.clone(),
+            Edit::Camera { to, .. } => *camera = *to,
+        }
+    }
+
+    fn inverse(&self) -> Self {
+        match self.clone() {
+            Edit::Add { index, body } => Edit::Remove { index, body },
+            Edit::Remove { index, body } => Edit::Add { index, body },
+            Edit::Move { index, from, to } => Edit::Move {
+                index,
+                from: to,
+                to: from,
+            },
+            Edit::Mass { index, from, to } => Edit::Mass {
+                index,
+                from: to,
+                to: from,
+            },
+            Edit::Color { index, from, to } => Edit::Color {
+                index,
+                from: to,
+                to: from,
+            },
+            Edit::Replace { index, from, to } => Edit::Replace {
+                index,
+                from: to,
+                to: from,
+            },
+            Edit::Camera { from, to } => Edit::Camera { from: to, to: from },
+        }
+    }
+}
+
+/// Edits turning body `from` into body `to`, both at `index`. A body with another name or id is
+/// replaced as a whole, like the generator replacing the scene does
+fn body_diff(index: usize, from: &Body, to: &Body, edits: &mut Vec<Edit>) {
+    if from.name != to.name || from.id != to.id {
+        edits.push(Edit::Replace {
+            index,
+            from: from.clone(),
+            to: to.clone(),
+        });
+        return;
+    }
+    if from.position != to.position {
+        edits.push(Edit::Move {
+            index,
+            from: from.position,
+            to: to.position,
+        });
+    }
+    if from.mass != to.mass {
+        edits.push(Edit::Mass {
+            index,
+            from: from.mass,
+            to: to.mass,
+        });
+    }
+    if from.color != to.color {
+        edits.push(Edit::Color {
+            index,
+            from: from.color,
+            to: to.color,
+        });
+    }
+}
+
+/// Where a body was inserted into `short` to make `long`, one longer. Picks the index leaving the
+/// fewest other bodies changed, so edits of other bodies in the same step don't move it
+fn insertion_index(short: &[Body], long: &[Body]) -> usize {
+    let changed = |index: usize| {
+        let before = short[..index].iter().zip(&long[..index]);
+        let after = short[index..].iter().zip(&long[index + 1..]);
+        before.chain(after).filter(|(a, b)| a != b).count()
+    };
+    (0..=short.len())
+        .min_by_key(|&index| changed(index))
+        .unwrap_or(0)
+}
+
+/// Each entry is a group of edits that is undone as one
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Vec<Edit>>,
+    redo: Vec<Vec<Edit>>,
+}
+impl History {
+    /// Record a group of edits that has already been applied
+    pub fn push(&mut self, edits: Vec<Edit>) {
+        if !edits.is_empty() {
+            self.undo.push(edits);
+            self.redo.clear();
+        }
+    }
+
+    /// Revert the last group of edits, returns false if there is nothing to undo
+    pub fn undo(&mut self, bodies: &mut Vec<Body>, camera: &mut Camera) -> bool {
+        let Some(edits) = self.undo.pop() else {
+            return false;
+        };
+        for edit in edits.iter().rev() {
+            edit.inverse().apply(bodies, camera);
+        }
+        self.redo.push(edits);
+        true
+    }
+
+    /// Reapply the last undone group of edits, returns false if there is nothing to redo
+    pub fn redo(&mut self, bodies: &mut Vec<Body>, camera: &mut Camera) -> bool {
+        let Some(edits) = self.redo.pop() else {
+            return false;
+        };
+        for edit in &edits {
+            edit.apply(bodies, camera);
+        }
+        self.undo.push(edits);
+        true
+    }
+
+    /// Edits turning the scene `before` into the scene `after`, in the order they have to be applied.
+    /// Only bodies and the camera are compared, solver settings aren't part of the history
+    pub fn diff(before: (&[Body], &Camera), after: (&[Body], &Camera)) -> Vec<Edit> {
+        let (before, &before_camera) = before;
+        let (after, &after_camera) = after;
+        let mut edits = Vec::new();
+
+        // front-ends add or remove a single body at a time, everything else is compared index by index
+        if after.len() == before.len() + 1 {
+            let index = insertion_index(before, after);
+            // the edits after it refer to indices after the insertion
+            edits.push(Edit::Add {
+                index,
+                body: after[index].clone(),
+            });
+            for (i, from) in before.iter().enumerate() {
+                let i = if i < index { i } else { i + 1 };
+                body_diff(i, from, &after[i], &mut edits);
+            }
+        } else if before.len() == after.len() + 1 {
+            let index = insertion_index(after, before);
+            edits.push(Edit::Remove {
+                index,
+                body: before[index].clone(),
+            });
+            for (i, to) in after.iter().enumerate() {
+                let from = &before[if i < index { i } else { i + 1 }];
+                body_diff(i, from, to, &mut edits);
+            }
+        } else {
+            for (i, (from, to)) in before.iter().zip(after).enumerate() {
+                body_diff(i, from, to, &mut edits);
+            }
+            for (index, body) in after.iter().enumerate().skip(before.len()) {
+                edits.push(Edit::Add {
+                    index,
+                    body: body.clone(),
+                });
+            }
+            for (index, body) in before.iter().enumerate().skip(after.len()).rev() {
+                edits.push(Edit::Remove {
+                    index,
+                    body: body.clone(),
+                });
+            }
+        }
+
+        if before_camera != after_camera {
+            edits.push(Edit::Camera {
+                from: before_camera,
+                to: after_camera,
+            });
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use gravity_basins::{generate::Generator, palette::Palette};
+    use spirv_std::glam::{dvec2, vec2, vec3};
+
+    fn bodies(count: u32) -> Vec<Body> {
+        (0..count)
+            .map(|i| Body {
+                id: Some(i),
+                name: Some(format!("body {i}")),
+                ..Body::new(vec2(i as f32 * 10., 0.), 1. + i as f32, vec3(1., 0., 0.))
+            })
+            .collect()
+    }
+
+    /// Record the edit from `before` to `after`, then check undo and redo restore both
+    fn round_trip(before: (Vec<Body>, Camera), after: (Vec<Body>, Camera)) {
+        let mut history = History::default();
+        history.push(History::diff((&before.0, &before.1), (&after.0, &after.1)));
+        let (mut bodies, mut camera) = after.clone();
+        assert!(history.undo(&mut bodies, &mut camera));
+        assert_eq!((&bodies, camera), (&before.0, before.1));
+        assert!(!history.undo(&mut bodies, &mut camera));
+        assert!(history.redo(&mut bodies, &mut camera));
+        assert_eq!((&bodies, camera), (&after.0, after.1));
+        assert!(!history.redo(&mut bodies, &mut camera));
+    }
+
+    #[test]
+    fn diff_add() {
+        let before = bodies(3);
+        let mut after = before.clone();
+        let added = Body::new(vec2(5., 5.), 2., vec3(0., 1., 0.));
+        after.insert(1, added.clone());
+        let camera = Camera::new(dvec2(0., 0.));
+        let edits = History::diff((&before, &camera), (&after, &camera));
+        assert_eq!(
+            edits,
+            [Edit::Add {
+                index: 1,
+                body: added
+            }]
+        );
+        round_trip((before, camera), (after, camera));
+    }
+
+    #[test]
+    fn diff_remove_keeps_name_and_id() {
+        let before = bodies(3);
+        let mut after = before.clone();
+        let removed = after.remove(1);
+        let camera = Camera::new(dvec2(0., 0.));
+        let edits = History::diff((&before, &camera), (&after, &camera));
+        assert_eq!(
+            edits,
+            [Edit::Remove {
+                index: 1,
+                body: removed
+            }]
+        );
+        round_trip((before, camera), (after, camera));
+    }
+
+    #[test]
+    fn replace_with_generator_then_undo() {
+        let before = bodies(3);
+        let camera = Camera::new(dvec2(0., 0.));
+        // replacing with as many, fewer and more bodies
+        for sides in [3, 2, 4] {
+            let mut after = Vec::new();
+            let polygon = Generator::Polygon {
+                sides,
+                radius: 100.,
+                mass: 1.,
+                rotation: 0.,
+            };
+            polygon.apply(&mut after, &Palette::builtin()[0]).unwrap();
+            let edits = History::diff((&before, &camera), (&after, &camera));
+            assert!(edits.iter().all(|edit| matches!(
+                edit,
+                Edit::Replace { .. } | Edit::Add { .. } | Edit::Remove { .. }
+            )));
+            round_trip((before.clone(), camera), (after, camera));
+        }
+    }
+
+    #[test]
+    fn add_with_an_earlier_move() {
+        let before = bodies(3);
+        let mut after = before.clone();
+        after[0].position = vec2(1., 1.);
+        let added = Body::new(vec2(5., 5.), 2., vec3(0., 1., 0.));
+        after.insert(2, added.clone());
+        let camera = Camera::new(dvec2(0., 0.));
+        let edits = History::diff((&before, &camera), (&after, &camera));
+        assert_eq!(
+            edits,
+            [
+                Edit::Add {
+                    index: 2,
+                    body: added
+                },
+                Edit::Move {
+                    index: 0,
+                    from: before[0].position,
+                    to: after[0].position
+                }
+            ]
+        );
+        round_trip((before, camera), (after, camera));
+    }
+
+    #[test]
+    fn diff_move() {
+        let before = bodies(3);
+        let mut after = before.clone();
+        after[2].position = vec2(-4., 7.);
+        let camera = Camera::new(dvec2(0., 0.));
+        let edits = History::diff((&before, &camera), (&after, &camera));
+        assert_eq!(
+            edits,
+            [Edit::Move {
+                index: 2,
+                from: before[2].position,
+                to: after[2].position
+            }]
+        );
+        round_trip((before, camera), (after, camera));
+    }
+
+    #[test]
+    fn remove_with_other_edits_and_camera() {
+        let before = bodies(4);
+        let mut after = before.clone();
+        after.remove(0);
+        after[1].mass = 10.;
+        after[2].color = vec3(0., 0., 1.);
+        let camera = Camera::new(dvec2(0., 0.));
+        let moved = Camera {
+            center: dvec2(3., 4.),
+            scale: 0.5,
+        };
+        round_trip((before, camera), (after, moved));
+    }
+
+    #[test]
+    fn push_after_undo_clears_redo() {
+        let (mut bodies, mut camera) = (bodies(2), Camera::new(dvec2(0., 0.)));
+        let mut history = History::default();
+        let moved = Camera::new(dvec2(1., 0.));
+        history.push(History::diff((&bodies, &camera), (&bodies, &moved)));
+        camera = moved;
+        history.push(Vec::new());
+        assert!(history.undo(&mut bodies, &mut camera));
+        history.push(History::diff((&bodies, &camera), (&bodies, &moved)));
+        assert!(!history.redo(&mut bodies, &mut camera));
+    }
+}
@@ -0,0 +1,55 @@
+//! Full path of a single particle, traced with the `f64` solver of the CPU reference renderer
+
+use shader::{double::Particle, Data, NO_GRAVITON};
+use spirv_std::glam::DVec2;
+
+pub struct Trajectory {
+    /// position after every step, starting with the release point
+    pub points: Vec<DVec2>,
+    /// index of the capturing graviton, or `NO_GRAVITON`
+    pub graviton: u32,
+    pub time: f64,
+    /// change in total energy between release and the last step, relative to the initial energy
+    pub energy_drift: f64,
+}
+impl Trajectory {
+    /// Trace a particle released at rest from `start`, exactly like `find_basin` does
+    pub fn trace(start: DVec2, data: &Data) -> Self {
+        let mut particle = Particle::new(start);
+        let initial_energy = particle.energy(data);
+        let mut points = vec![start];
+        let mut graviton = NO_GRAVITON;
+        for _ in 0..data.max_steps {
+            graviton = particle.step(data);
+            if graviton != NO_GRAVITON {
+                break;
+            }
+            points.push(particle.coord);
+        }
+
+        Self {
+            points,
+            graviton,
+            time: particle.time,
+            energy_drift: (particle.energy(data) - initial_energy) / initial_energy.abs(),
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.points.len() - 1
+    }
+
+    pub fn summary(&self) -> String {
+        let capture = if self.graviton == NO_GRAVITON {
+            "not captured".to_owned()
+        } else {
+            format!("captured by graviton {}", self.graviton)
+        };
+        format!(
+            "{capture} after {} steps, t = {:.3}, energy drift {:.2e}",
+            self.steps(),
+            self.time,
+            self.energy_drift
+        )
+    }
+}
@@ -0,0 +1,20 @@
+//! Basins of attraction of gravitating point masses: scene files, GPU and CPU renderers,
+//! analysis of the results and exports. The `gravity-basins` binary is a viewer built on this.
+
+pub mod analysis;
+pub mod camera;
+pub mod cpu;
+pub mod export;
+pub mod generate;
+pub mod gpu;
+pub mod hdr;
+pub mod palette;
+pub mod readback;
+pub mod renderer;
+pub mod scene;
+pub mod stats;
+pub mod svg;
+pub mod timing;
+
+pub use renderer::{BasinRenderer, Precision};
+pub use shader;
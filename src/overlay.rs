@@ -0,0 +1,298 @@
+//! Lines drawn on top of the basin texture
+
+use crate::inspector::Trajectory;
+use bytemuck::{Pod, Zeroable};
+use gravity_basins::camera::Camera;
+use shader::{Data, Graviton, NO_GRAVITON};
+use spirv_std::glam::{dvec2, DVec2};
+use std::f64::consts::TAU;
+use wgpu::util::DeviceExt;
+
+const WHITE: [f32; 4] = [1., 1., 1., 0.9];
+const SELECTION_COLOR: [f32; 4] = [1., 0.85, 0., 1.];
+
+/// Which parts of the overlay are drawn
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Toggles {
+    pub gravitons: bool,
+    pub capture_radii: bool,
+    pub selection: bool,
+    pub scale_bar: bool,
+    pub trajectory: bool,
+    /// frame and compute time in the corner of the window, drawn by the gui
+    pub timings: bool,
+    /// share and mean capture time of each basin next to the timings, once computed
+    pub basin_stats: bool,
+}
+impl Default for Toggles {
+    fn default() -> Self {
+        Self {
+            gravitons: true,
+            capture_radii: false,
+            selection: true,
+            scale_bar: true,
+            trajectory: true,
+            timings: true,
+            basin_stats: true,
+        }
+    }
+}
+
+#[derive(Pod, Zeroable, Clone, Copy)]
+#[repr(C)]
+pub struct LineVertex {
+    /// clip space
+    pub position: [f32; 2],
+    pub color: [f32; 4],
+}
+impl LineVertex {
+    const LAYOUT: wgpu::VertexBufferLayout<'static> = wgpu::VertexBufferLayout {
+        array_stride: size_of::<Self>() as wgpu::BufferAddress,
+        step_mode: wgpu::VertexStepMode::Vertex,
+        attributes: &wgpu::vertex_attr_array![0 => Float32x2, 1 => Float32x4],
+    };
+}
+
+/// Line segments in pixel coordinates
+pub struct Lines {
+    segments: Vec<([DVec2; 2], [f32; 4])>,
+    camera: Camera,
+    /// of the render target, in pixels
+    size: DVec2,
+}
+impl Lines {
+    pub fn new(camera: Camera, size: DVec2) -> Self {
+        Self {
+            segments: Vec::new(),
+            camera,
+            size,
+        }
+    }
+
+    fn to_pixel(&self, world: DVec2) -> DVec2 {
+        self.camera.world_to_pixel(world, self.size)
+    }
+
+    pub fn segment(&mut self, from: DVec2, to: DVec2, color: [f32; 4]) {
+        self.segments.push(([from, to], color));
+    }
+
+    pub fn polyline(&mut self, points: &[DVec2], color: [f32; 4]) {
+        for pair in points.windows(2) {
+            self.segment(pair[0], pair[1], color);
+        }
+    }
+
+    /// Diagonal cross marking `center`
+    pub fn cross(&mut self, center: DVec2, radius: f64, color: [f32; 4]) {
+        self.segment(center - radius, center + radius, color);
+        self.segment(
+            center + dvec2(-radius, radius),
+            center + dvec2(radius, -radius),
+            color,
+        );
+    }
+
+    pub fn circle(&mut self, center: DVec2, radius: f64, color: [f32; 4]) {
+        let points: Vec<_> = (0..=CIRCLE_SEGMENTS)
+            .map(|i| circle_point(center, radius, i, CIRCLE_SEGMENTS))
+            .collect();
+        self.polyline(&points, color);
+    }
+
+    /// Circle with dashes of roughly `dash` pixels
+    pub fn dashed_circle(&mut self, center: DVec2, radius: f64, dash: f64, color: [f32; 4]) {
+        // even number of segments, so the dashes line up where the circle closes
+        let segments = ((TAU * radius / dash / 2.).ceil() as usize * 2).clamp(8, 512);
+        for i in (0..segments).step_by(2) {
+            self.segment(
+                circle_point(center, radius, i, segments),
+                circle_point(center, radius, i + 1, segments),
+                color,
+            );
+        }
+    }
+
+    /// A circle per graviton, with an area proportional to its mass
+    pub fn gravitons(&mut self, data: &Data) {
+        for graviton in &data.gravitons[..data.num_gravitons as usize] {
+            let center = self.to_pixel(graviton_position(graviton));
+            let radius = graviton_radius(graviton);
+            self.circle(center, radius, WHITE);
+            let color = [graviton.color_r, graviton.color_g, graviton.color_b, 1.];
+            self.circle(center, radius - 1.5, color);
+        }
+    }
+
+    /// Distance at which the gravitons capture particles
+    pub fn capture_radii(&mut self, data: &Data) {
+        let radius = data.capture_radius as f64 / self.camera.scale;
+        for graviton in &data.gravitons[..data.num_gravitons as usize] {
+            let center = self.to_pixel(graviton_position(graviton));
+            self.dashed_circle(center, radius, 6., WHITE);
+        }
+    }
+
+    pub fn selection(&mut self, graviton: &Graviton) {
+        let center = self.to_pixel(graviton_position(graviton));
+        let radius = graviton_radius(graviton) + 4.;
+        self.circle(center, radius, SELECTION_COLOR);
+        self.circle(center, radius + 1., SELECTION_COLOR);
+    }
+
+    /// Bar in the bottom left corner, returns the world length it represents
+    pub fn scale_bar(&mut self) -> f64 {
+        let length = scale_bar_length(self.camera.scale, self.size.x / 5.);
+        let pixels = length / self.camera.scale;
+        let start = dvec2(20., self.size.y - 20.);
+        let end = start + dvec2(pixels, 0.);
+        self.segment(start, end, WHITE);
+        for tick in [0., 0.5, 1.] {
+            let x = start.x + pixels * tick;
+            let height = if tick == 0.5 { 3. } else { 6. };
+            self.segment(dvec2(x, start.y - height), dvec2(x, start.y), WHITE);
+        }
+        length
+    }
+
+    /// Path with a marker for every step, and the capturing graviton
+    pub fn trajectory(&mut self, trajectory: &Trajectory, data: &Data) {
+        let points: Vec<_> = trajectory
+            .points
+            .iter()
+            .map(|&point| self.to_pixel(point))
+            .collect();
+        self.polyline(&points, WHITE);
+        for &point in &points {
+            self.cross(point, 2., WHITE);
+        }
+        if trajectory.graviton != NO_GRAVITON {
+            let graviton = &data.gravitons[trajectory.graviton as usize];
+            let color = [graviton.color_r, graviton.color_g, graviton.color_b, 1.];
+            let center = self.to_pixel(graviton_position(graviton));
+            self.circle(
+                center,
+                data.capture_radius as f64 / self.camera.scale,
+                color,
+            );
+            self.cross(*points.last().unwrap(), 6., color);
+        }
+    }
+
+    /// Convert to clip space
+    fn vertices(&self) -> Vec<LineVertex> {
+        self.segments
+            .iter()
+            .flat_map(|&(points, color)| {
+                points.map(|point| {
+                    let clip = point / self.size * 2. - 1.;
+                    LineVertex {
+                        // pixels go down, clip space goes up
+                        position: [clip.x as f32, -clip.y as f32],
+                        color,
+                    }
+                })
+            })
+            .collect()
+    }
+}
+
+const CIRCLE_SEGMENTS: usize = 64;
+
+fn circle_point(center: DVec2, radius: f64, i: usize, segments: usize) -> DVec2 {
+    center + DVec2::from_angle(i as f64 / segments as f64 * TAU) * radius
+}
+
+fn graviton_position(graviton: &Graviton) -> DVec2 {
+    dvec2(graviton.position_x as f64, graviton.position_y as f64)
+}
+
+/// Radius of the circle drawn for `graviton`, in pixels
+pub fn graviton_radius(graviton: &Graviton) -> f64 {
+    8. * (graviton.mass.max(0.) as f64).sqrt()
+}
+
+/// Largest 1, 2 or 5 times a power of ten that is shorter than `max_pixels`
+fn scale_bar_length(scale: f64, max_pixels: f64) -> f64 {
+    let max_length = max_pixels * scale;
+    let magnitude = 10f64.powf(max_length.log10().floor());
+    [5., 2., 1.]
+        .into_iter()
+        .map(|factor| factor * magnitude)
+        .find(|&length| length <= max_length)
+        .unwrap_or(magnitude)
+}
+
+pub struct Overlay {
+    pipeline: wgpu::RenderPipeline,
+    vertex_buffer: Option<wgpu::Buffer>,
+    vertex_count: u32,
+}
+impl Overlay {
+    pub fn new(
+        device: &wgpu::Device,
+        shader: &wgpu::ShaderModule,
+        format: wgpu::TextureFormat,
+    ) -> Self {
+        let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Overlay Pipeline Layout"),
+            bind_group_layouts: &[],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Overlay Pipeline"),
+            layout: Some(&layout),
+            vertex: wgpu::VertexState {
+                module: shader,
+                entry_point: Some("vs_line"),
+                buffers: &[LineVertex::LAYOUT],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: shader,
+                entry_point: Some("fs_line"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::LineList,
+                ..Default::default()
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            vertex_buffer: None,
+            vertex_count: 0,
+        }
+    }
+
+    /// Replace the drawn lines
+    pub fn set_lines(&mut self, device: &wgpu::Device, lines: &Lines) {
+        let vertices = lines.vertices();
+        self.vertex_count = vertices.len() as u32;
+        self.vertex_buffer = (!vertices.is_empty()).then(|| {
+            device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+                label: Some("Overlay Vertex Buffer"),
+                contents: bytemuck::cast_slice(&vertices),
+                usage: wgpu::BufferUsages::VERTEX,
+            })
+        });
+    }
+
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        if let Some(vertex_buffer) = &self.vertex_buffer {
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.draw(0..self.vertex_count, 0..1);
+        }
+    }
+}
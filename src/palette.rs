@@ -0,0 +1,309 @@
+//! Named palettes, used to color gravitons and to shade by capture time.
+//! Colors are stored in linear RGB, like the graviton colors in `Data`
+
+use crate::scene::Body;
+use anyhow::{bail, Context};
+use shader::PALETTE_SIZE;
+use spirv_std::glam::Vec3;
+use std::{fs, path::Path};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PaletteKind {
+    /// distinct colors, handed out in order
+    Categorical,
+    /// a smooth ramp, sampled evenly
+    Sequential,
+}
+
+#[derive(Clone, Debug)]
+pub struct Palette {
+    pub name: String,
+    pub kind: PaletteKind,
+    pub colors: Vec<[f32; 3]>,
+}
+impl Palette {
+    fn from_hex(name: &str, kind: PaletteKind, colors: &[u32]) -> Self {
+        Self {
+            name: name.to_owned(),
+            kind,
+            colors: colors
+                .iter()
+                .map(|&hex| srgb_bytes_to_linear([(hex >> 16) as u8, (hex >> 8) as u8, hex as u8]))
+                .collect(),
+        }
+    }
+
+    pub fn builtin() -> Vec<Self> {
+        use PaletteKind::*;
+        vec![
+            Self::from_hex(
+                "tableau10",
+                Categorical,
+                &[
+                    0x4e79a7, 0xf28e2b, 0xe15759, 0x76b7b2, 0x59a14f, 0xedc948, 0xb07aa1, 0xff9da7,
+                    0x9c755f, 0xbab0ac,
+                ],
+            ),
+            Self::from_hex(
+                "set1",
+                Categorical,
+                &[
+                    0xe41a1c, 0x377eb8, 0x4daf4a, 0x984ea3, 0xff7f00, 0xffff33, 0xa65628, 0xf781bf,
+                    0x999999,
+                ],
+            ),
+            Self::from_hex(
+                "viridis",
+                Sequential,
+                &[
+                    0x440154, 0x472d7b, 0x3b528b, 0x2c728e, 0x21918c, 0x28ae80, 0x5ec962, 0xaddc30,
+                    0xfde725,
+                ],
+            ),
+            Self::from_hex(
+                "magma",
+                Sequential,
+                &[
+                    0x000004, 0x1c1044, 0x4f127b, 0x812581, 0xb5367a, 0xe55064, 0xfb8761, 0xfec287,
+                    0xfcfdbf,
+                ],
+            ),
+            Self::from_hex(
+                "inferno",
+                Sequential,
+                &[
+                    0x000004, 0x1f0c48, 0x550f6d, 0x88226a, 0xba3655, 0xe35933, 0xf98e09, 0xf8c932,
+                    0xfcffa4,
+                ],
+            ),
+        ]
+    }
+
+    /// Load a GIMP `.gpl`, Fractint `.map` or `.csv` palette, picking the format by extension
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read palette {}", path.display()))?;
+        let extension = path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase);
+        let colors = match extension.as_deref() {
+            Some("gpl") => parse_gpl(&text),
+            Some("map") => parse_map(&text),
+            Some("csv") => parse_csv(&text),
+            _ => bail!("Unknown palette format {}", path.display()),
+        }
+        .with_context(|| format!("Failed to parse palette {}", path.display()))?;
+        if colors.is_empty() {
+            bail!("Palette {} has no colors", path.display());
+        }
+
+        // fractint maps are gradients, short lists are usually meant for distinct basins
+        let kind = if extension.as_deref() == Some("map") || colors.len() > 32 {
+            PaletteKind::Sequential
+        } else {
+            PaletteKind::Categorical
+        };
+        let name = path.file_stem().map_or_else(
+            || "imported".to_owned(),
+            |stem| stem.to_string_lossy().into_owned(),
+        );
+        Ok(Self { name, kind, colors })
+    }
+
+    /// Color at `t` in `0..=1`, interpolating between neighbouring colors
+    pub fn sample(&self, t: f32) -> [f32; 3] {
+        let position = t.clamp(0., 1.) * (self.colors.len() - 1) as f32;
+        let index = (position as usize).min(self.colors.len() - 1);
+        let next = (index + 1).min(self.colors.len() - 1);
+        let fraction = position - index as f32;
+        let [a, b] = [self.colors[index], self.colors[next]];
+        [0, 1, 2].map(|i| a[i] + (b[i] - a[i]) * fraction)
+    }
+
+    /// Color for graviton `index` out of `count`
+    pub fn graviton_color(&self, index: usize, count: usize) -> [f32; 3] {
+        match self.kind {
+            PaletteKind::Categorical => self.colors[index % self.colors.len()],
+            PaletteKind::Sequential if count > 1 => self.sample(index as f32 / (count - 1) as f32),
+            PaletteKind::Sequential => self.sample(0.5),
+        }
+    }
+
+    /// Recolor all `bodies`
+    pub fn apply(&self, bodies: &mut [Body]) {
+        let count = bodies.len();
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.color = Vec3::from_array(self.graviton_color(i, count));
+        }
+    }
+
+    /// Texels for the palette texture `fs_main` shades with
+    pub fn texels(&self) -> Vec<[f32; 4]> {
+        (0..PALETTE_SIZE)
+            .map(|i| {
+                let [r, g, b] = self.sample(i as f32 / (PALETTE_SIZE - 1) as f32);
+                [r, g, b, 1.]
+            })
+            .collect()
+    }
+}
+
+fn srgb_to_linear(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+fn srgb_bytes_to_linear(color: [u8; 3]) -> [f32; 3] {
+    color.map(|channel| srgb_to_linear(channel as f32 / 255.))
+}
+
+pub fn linear_to_srgb(value: f32) -> f32 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1. / 2.4) - 0.055
+    }
+}
+
+/// Inverse of `srgb_bytes_to_linear`, for writing colors to files
+pub fn linear_to_srgb_bytes(color: [f32; 3]) -> [u8; 3] {
+    color.map(|channel| (linear_to_srgb(channel).clamp(0., 1.) * 255.).round() as u8)
+}
+
+/// Parse the first three whitespace separated fields of `line` as 0-255 channels
+fn parse_bytes(line: &str) -> anyhow::Result<[u8; 3]> {
+    let mut fields = line.split_whitespace();
+    let mut channel = || -> anyhow::Result<u8> {
+        let field = fields.next().context("Expected three channels")?;
+        field
+            .parse()
+            .with_context(|| format!("Invalid channel {field:?}"))
+    };
+    Ok([channel()?, channel()?, channel()?])
+}
+
+/// `GIMP Palette` header, `Name:`/`Columns:` lines and `#` comments, then `r g b name` per line
+fn parse_gpl(text: &str) -> anyhow::Result<Vec<[f32; 3]>> {
+    let mut lines = text.lines();
+    if lines.next().map(str::trim) != Some("GIMP Palette") {
+        bail!("Missing \"GIMP Palette\" header");
+    }
+    lines
+        .map(str::trim)
+        .filter(|line| {
+            !(line.is_empty()
+                || line.starts_with('#')
+                || line.starts_with("Name:")
+                || line.starts_with("Columns:"))
+        })
+        .map(|line| parse_bytes(line).map(srgb_bytes_to_linear))
+        .collect()
+}
+
+/// `r g b` per line, anything after the channels is a comment
+fn parse_map(text: &str) -> anyhow::Result<Vec<[f32; 3]>> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| parse_bytes(line).map(srgb_bytes_to_linear))
+        .collect()
+}
+
+/// `r,g,b` per line, either as 0-255 integers or as 0-1 floats, an optional header line is skipped.
+/// The whole file is normalized if any channel has a decimal point or none is above 1
+fn parse_csv(text: &str) -> anyhow::Result<Vec<[f32; 3]>> {
+    let mut lines = text
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .peekable();
+    if let Some(header) = lines.peek() {
+        if header
+            .split(',')
+            .next()
+            .is_some_and(|field| field.trim().parse::<f32>().is_err())
+        {
+            lines.next();
+        }
+    }
+
+    let rows = lines
+        .map(|line| {
+            let fields: Vec<_> = line.split(',').map(str::trim).collect();
+            let [r, g, b] = fields[..] else {
+                bail!("Expected three columns in {line:?}");
+            };
+            let channel = |field: &str| -> anyhow::Result<f32> {
+                field
+                    .parse()
+                    .with_context(|| format!("Invalid channel {field:?}"))
+            };
+            Ok(([channel(r)?, channel(g)?, channel(b)?], line.contains('.')))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+
+    let normalized = rows.iter().any(|&(_, decimal)| decimal)
+        || rows
+            .iter()
+            .flat_map(|(row, _)| row)
+            .all(|&value| value <= 1.);
+    let scale = if normalized { 1. } else { 1. / 255. };
+    Ok(rows
+        .into_iter()
+        .map(|(row, _)| row.map(|value| srgb_to_linear(value * scale)))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bytes(colors: Vec<[f32; 3]>) -> Vec<[u8; 3]> {
+        colors.into_iter().map(linear_to_srgb_bytes).collect()
+    }
+
+    #[test]
+    fn gpl() {
+        let text =
+            "GIMP Palette\nName: test\nColumns: 2\n# comment\n255 0 0 red\n  0 128 255\tblue\n";
+        assert_eq!(
+            bytes(parse_gpl(text).unwrap()),
+            [[255, 0, 0], [0, 128, 255]]
+        );
+        assert!(parse_gpl("255 0 0\n").is_err());
+    }
+
+    #[test]
+    fn map() {
+        let text = "0 0 0\n\n255 255 255 white\n";
+        assert_eq!(
+            bytes(parse_map(text).unwrap()),
+            [[0, 0, 0], [255, 255, 255]]
+        );
+        assert!(parse_map("0 0\n").is_err());
+    }
+
+    #[test]
+    fn csv_bytes() {
+        let text = "r,g,b\n255, 0, 128\n1,0,0\n";
+        assert_eq!(bytes(parse_csv(text).unwrap()), [[255, 0, 128], [1, 0, 0]]);
+    }
+
+    #[test]
+    fn csv_normalized() {
+        assert_eq!(
+            bytes(parse_csv("1,0,0.25\n0,0,1\n").unwrap()),
+            [[255, 0, 64], [0, 0, 255]]
+        );
+        assert_eq!(bytes(parse_csv("0,0,1\n").unwrap()), [[0, 0, 255]]);
+    }
+
+    #[test]
+    fn csv_columns() {
+        assert!(parse_csv("1,0\n").is_err());
+    }
+}
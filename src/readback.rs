@@ -0,0 +1,130 @@
+//! Copying textures back to the CPU for analysis and export
+
+use bytemuck::Pod;
+use std::{
+    marker::PhantomData,
+    sync::{Arc, OnceLock},
+};
+
+/// Copy of a texture region on its way to the CPU
+pub struct Readback<T> {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    bytes_per_row: u32,
+    /// set by the `map_async` callback
+    mapped: Arc<OnceLock<Result<(), wgpu::BufferAsyncError>>>,
+    texel: PhantomData<T>,
+}
+impl<T: Pod> Readback<T> {
+    /// Submit a copy of the top left `width` x `height` texels of `texture` and request the
+    /// mapping, without waiting for either. The region must not be empty.
+    /// `T` has to match the texel size of the texture format
+    pub fn start(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        texture: &wgpu::Texture,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let texel_size = size_of::<T>() as u32;
+        assert_eq!(
+            Some(texel_size),
+            texture.format().block_copy_size(None),
+            "texel type doesn't match the texture format"
+        );
+        let size = texture.size();
+        let (width, height) = (width.min(size.width), height.min(size.height));
+
+        // buffer rows have to be aligned, the padding is dropped again in `texels`
+        let bytes_per_row =
+            (width * texel_size).next_multiple_of(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT);
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Readback Buffer"),
+            size: (bytes_per_row * height) as u64,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Readback Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            texture.as_image_copy(),
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(bytes_per_row),
+                    rows_per_image: Some(height),
+                },
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let mapped = Arc::new(OnceLock::new());
+        let callback_mapped = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                callback_mapped.set(result).ok();
+            });
+        Self {
+            buffer,
+            width,
+            height,
+            bytes_per_row,
+            mapped,
+            texel: PhantomData,
+        }
+    }
+
+    /// Whether the mapping is done, which only happens while the device is polled
+    pub fn is_done(&self) -> bool {
+        self.mapped.get().is_some()
+    }
+
+    /// The texels, once `is_done`
+    pub fn finish(self) -> Result<Vec<T>, wgpu::BufferAsyncError> {
+        let result = self.mapped.get().expect("readback isn't done yet").clone();
+        result.map(|()| {
+            let mapped = self.buffer.slice(..).get_mapped_range();
+            let unpadded_bytes_per_row = (self.width as usize) * size_of::<T>();
+            let mut texels = Vec::with_capacity((self.width * self.height) as usize);
+            for row in mapped.chunks_exact(self.bytes_per_row as usize) {
+                texels.extend_from_slice(bytemuck::cast_slice(&row[..unpadded_bytes_per_row]));
+            }
+            drop(mapped);
+            self.buffer.unmap();
+            texels
+        })
+    }
+}
+
+/// Read the top left `width` x `height` texels of `texture`, blocking until the copy is done.
+/// `T` has to match the texel size of the texture format
+pub fn read_texture<T: Pod>(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    texture: &wgpu::Texture,
+    width: u32,
+    height: u32,
+) -> Vec<T> {
+    let size = texture.size();
+    if width.min(size.width) == 0 || height.min(size.height) == 0 {
+        return Vec::new();
+    }
+    let readback = Readback::start(device, queue, texture, width, height);
+    device
+        .poll(wgpu::PollType::Wait)
+        .expect("Failed to wait for the readback");
+    readback.finish().unwrap_or_else(|error| {
+        log::error!("Failed to map readback buffer: {error}");
+        Vec::new()
+    })
+}
@@ -0,0 +1,757 @@
+//! GPU basin rendering without a window: solves a `Data` view into a result texture and
+//! colors it into any render target
+
+use crate::{palette::Palette, readback};
+use bytemuck::{Pod, Zeroable};
+use shader::{Data, PALETTE_SIZE};
+use wgpu::{include_spirv, util::DeviceExt, BindingResource};
+
+/// Arithmetic used by the compute shader
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Precision {
+    F32,
+    /// two `f32`s per value, works everywhere
+    DoubleFloat,
+    /// native `f64`, only if the adapter supports `SHADER_F64`
+    F64,
+}
+
+/// Result and boundary textures of one view, with the pipelines that fill and color them
+pub struct BasinRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    /// last view passed to `set_data`
+    data: Data,
+    data_buffer: wgpu::Buffer,
+    shader: wgpu::ShaderModule,
+    compute_pipeline: wgpu::ComputePipeline,
+    compute_pipeline_df: wgpu::ComputePipeline,
+    compute_pipeline_f64: Option<wgpu::ComputePipeline>,
+    /// written by `cs_main`, see `Capture::to_texel`
+    storage_texture: wgpu::Texture,
+    /// velocity of each pixel's particle when captured, written by `cs_main` next to the results
+    velocity_texture: wgpu::Texture,
+    compute_bind_group: wgpu::BindGroup,
+    /// pixels next to a different basin, written by `cs_boundary`
+    boundary_texture: wgpu::Texture,
+    boundary_pipeline: wgpu::ComputePipeline,
+    boundary_bind_group: wgpu::BindGroup,
+    /// `cs_difference`, its bind group depends on the other renderer
+    difference_pipeline: wgpu::ComputePipeline,
+    difference_bind_group_layout: wgpu::BindGroupLayout,
+    palette_texture: wgpu::Texture,
+    render_pipeline: wgpu::RenderPipeline,
+    render_bind_group: wgpu::BindGroup,
+}
+
+impl BasinRenderer {
+    /// Textures for views of up to `width` x `height` pixels, colored into render targets of
+    /// `target_format`. The native `f64` solver is available if `device` has `SHADER_F64`.
+    pub fn new(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        target_format: wgpu::TextureFormat,
+        width: u32,
+        height: u32,
+    ) -> Self {
+        let data = Data::zeroed();
+        let data_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Data Buffer"),
+            contents: bytemuck::cast_slice(&[data]),
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+        });
+        let texture_size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        let storage_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Texture"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::STORAGE_BINDING // compute
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST
+                | wgpu::TextureUsages::TEXTURE_BINDING, // fragment
+            view_formats: &[wgpu::TextureFormat::Rgba32Float],
+        });
+        let storage_view = storage_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let velocity_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Velocities"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rg32Float,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let velocity_view = velocity_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let compute_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rgba32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::Rg32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+                label: Some("bind_group_layout"),
+            });
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            layout: &compute_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&storage_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&velocity_view),
+                },
+            ],
+            label: Some("bind_group"),
+        });
+
+        let shader = device.create_shader_module(include_spirv!(env!("shader.spv")));
+
+        let compute_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Compute Pipeline Layout"),
+                bind_group_layouts: &[&compute_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let create_compute_pipeline = |module: &wgpu::ShaderModule, entry_point: &str| {
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                compilation_options: Default::default(),
+                cache: None,
+                label: Some(entry_point),
+                layout: Some(&compute_pipeline_layout),
+                module,
+                entry_point: Some(entry_point),
+            })
+        };
+        let compute_pipeline = create_compute_pipeline(&shader, "cs_main");
+        let compute_pipeline_df = create_compute_pipeline(&shader, "cs_main_df");
+        let compute_pipeline_f64 =
+            device
+                .features()
+                .contains(wgpu::Features::SHADER_F64)
+                .then(|| {
+                    let shader_f64 =
+                        device.create_shader_module(include_spirv!(env!("shader_f64.spv")));
+                    create_compute_pipeline(&shader_f64, "cs_main_f64")
+                });
+
+        let boundary_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Boundaries"),
+            size: texture_size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R32Float,
+            usage: wgpu::TextureUsages::STORAGE_BINDING
+                | wgpu::TextureUsages::COPY_SRC
+                | wgpu::TextureUsages::COPY_DST // Wada points
+                | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let boundary_view = boundary_texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let boundary_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Boundary bind group"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::R32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let boundary_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Boundary bind group"),
+            layout: &boundary_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&storage_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&boundary_view),
+                },
+            ],
+        });
+        let boundary_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Boundary Pipeline Layout"),
+                bind_group_layouts: &[&boundary_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let boundary_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            compilation_options: Default::default(),
+            cache: None,
+            label: Some("cs_boundary"),
+            layout: Some(&boundary_pipeline_layout),
+            module: &shader,
+            entry_point: Some("cs_boundary"),
+        });
+
+        let results_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::COMPUTE,
+            ty: wgpu::BindingType::Texture {
+                sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let difference_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Difference bind group"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    results_entry(1),
+                    results_entry(2),
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::COMPUTE,
+                        ty: wgpu::BindingType::StorageTexture {
+                            access: wgpu::StorageTextureAccess::WriteOnly,
+                            format: wgpu::TextureFormat::R32Float,
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let difference_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Difference Pipeline Layout"),
+                bind_group_layouts: &[&difference_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+        let difference_pipeline =
+            device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+                compilation_options: Default::default(),
+                cache: None,
+                label: Some("cs_difference"),
+                layout: Some(&difference_pipeline_layout),
+                module: &shader,
+                entry_point: Some("cs_difference"),
+            });
+
+        let palette_texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Palette"),
+            size: wgpu::Extent3d {
+                width: PALETTE_SIZE,
+                height: 1,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D1,
+            format: wgpu::TextureFormat::Rgba32Float,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let palette_view = palette_texture.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let render_bind_group_layout =
+            device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+                label: Some("Render bind group"),
+                entries: &[
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D1,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Buffer {
+                            ty: wgpu::BufferBindingType::Uniform,
+                            has_dynamic_offset: false,
+                            min_binding_size: None,
+                        },
+                        count: None,
+                    },
+                    wgpu::BindGroupLayoutEntry {
+                        binding: 3,
+                        visibility: wgpu::ShaderStages::FRAGMENT,
+                        ty: wgpu::BindingType::Texture {
+                            sample_type: wgpu::TextureSampleType::Float { filterable: false },
+                            view_dimension: wgpu::TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                ],
+            });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Render bind group"),
+            layout: &render_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: BindingResource::TextureView(&storage_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&palette_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&boundary_view),
+                },
+            ],
+        });
+
+        let render_pipeline_layout =
+            device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                label: Some("Render Pipeline Layout"),
+                bind_group_layouts: &[&render_bind_group_layout],
+                push_constant_ranges: &[],
+            });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Render Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: target_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                // all
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+        Self {
+            device: device.clone(),
+            queue: queue.clone(),
+            data,
+            data_buffer,
+            shader,
+            compute_pipeline,
+            compute_pipeline_df,
+            compute_pipeline_f64,
+            storage_texture,
+            velocity_texture,
+            compute_bind_group,
+            boundary_texture,
+            boundary_pipeline,
+            boundary_bind_group,
+            difference_pipeline,
+            difference_bind_group_layout,
+            palette_texture,
+            render_pipeline,
+            render_bind_group,
+        }
+    }
+
+    /// Module with all entry points, for pipelines drawing on top of the basins
+    pub fn shader(&self) -> &wgpu::ShaderModule {
+        &self.shader
+    }
+
+    pub fn supports(&self, precision: Precision) -> bool {
+        precision != Precision::F64 || self.compute_pipeline_f64.is_some()
+    }
+
+    pub fn data(&self) -> &Data {
+        &self.data
+    }
+
+    /// Upload `data`, takes effect for coloring right away and for the results with the next `compute`
+    pub fn set_data(&mut self, data: &Data) {
+        self.data = *data;
+        self.queue
+            .write_buffer(&self.data_buffer, 0, bytemuck::cast_slice(&[*data]));
+    }
+
+    /// Upload `palette` as the palette `fs_main` shades with
+    pub fn set_palette(&self, palette: &Palette) {
+        self.queue.write_texture(
+            self.palette_texture.as_image_copy(),
+            bytemuck::cast_slice(&palette.texels()),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(PALETTE_SIZE * size_of::<[f32; 4]>() as u32),
+                rows_per_image: None,
+            },
+            self.palette_texture.size(),
+        );
+    }
+
+    /// Part of the view the textures hold
+    pub fn size(&self) -> (u32, u32) {
+        let size = self.storage_texture.size();
+        (
+            self.data.width.min(size.width),
+            self.data.height.min(size.height),
+        )
+    }
+
+    pub fn results_texture(&self) -> &wgpu::Texture {
+        &self.storage_texture
+    }
+
+    pub fn boundary_texture(&self) -> &wgpu::Texture {
+        &self.boundary_texture
+    }
+
+    /// Solve every pixel of the view with `precision` and rebuild the boundary mask.
+    /// Panics if `precision` isn't `supports`ed.
+    pub fn compute(&self, encoder: &mut wgpu::CommandEncoder, precision: Precision) {
+        self.compute_timed(encoder, precision, None);
+    }
+
+    /// `compute`, with timestamps around the solver pass, see `timing::GpuTimer`
+    pub fn compute_timed(
+        &self,
+        encoder: &mut wgpu::CommandEncoder,
+        precision: Precision,
+        timestamp_writes: Option<wgpu::ComputePassTimestampWrites>,
+    ) {
+        let (width, height) = self.size();
+        {
+            let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("Compute Pass"),
+                timestamp_writes,
+            });
+            let pipeline = match precision {
+                Precision::F32 => &self.compute_pipeline,
+                Precision::DoubleFloat => &self.compute_pipeline_df,
+                Precision::F64 => self.compute_pipeline_f64.as_ref().unwrap(),
+            };
+            compute_pass.set_pipeline(pipeline);
+            compute_pass.set_bind_group(0, &self.compute_bind_group, &[]);
+            let group_size = (16, 16);
+            compute_pass.dispatch_workgroups(
+                width.div_ceil(group_size.0),
+                height.div_ceil(group_size.1),
+                1,
+            );
+        }
+        self.compute_boundaries(encoder);
+    }
+
+    /// Rebuild the boundary mask from the current results
+    pub fn compute_boundaries(&self, encoder: &mut wgpu::CommandEncoder) {
+        let (width, height) = self.size();
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Boundary Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.boundary_pipeline);
+        compute_pass.set_bind_group(0, &self.boundary_bind_group, &[]);
+        let group_size = (16, 16);
+        compute_pass.dispatch_workgroups(
+            width.div_ceil(group_size.0),
+            height.div_ceil(group_size.1),
+            1,
+        );
+    }
+
+    /// Replace the boundary mask with the pixels whose label differs in the results of `other`,
+    /// which has to hold the same view. Shown with `BoundaryMode::DIFFERENCE`
+    pub fn compute_difference(&self, encoder: &mut wgpu::CommandEncoder, other: &BasinRenderer) {
+        let view = |texture: &wgpu::Texture| texture.create_view(&Default::default());
+        let (results, other_results, boundaries) = (
+            view(&self.storage_texture),
+            view(&other.storage_texture),
+            view(&self.boundary_texture),
+        );
+        let bind_group = self.device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Difference bind group"),
+            layout: &self.difference_bind_group_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: self.data_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: BindingResource::TextureView(&results),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: BindingResource::TextureView(&other_results),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 3,
+                    resource: BindingResource::TextureView(&boundaries),
+                },
+            ],
+        });
+        let (width, height) = self.size();
+        let mut compute_pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("Difference Pass"),
+            timestamp_writes: None,
+        });
+        compute_pass.set_pipeline(&self.difference_pipeline);
+        compute_pass.set_bind_group(0, &bind_group, &[]);
+        compute_pass.dispatch_workgroups(width.div_ceil(16), height.div_ceil(16), 1);
+    }
+
+    /// `compute` on its own submission
+    pub fn submit_compute(&self, precision: Precision) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Encoder"),
+            });
+        self.compute(&mut encoder, precision);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Color the results into `render_pass`, whose viewport covers the view and starts
+    /// `Data::viewport_x` pixels from the left of the target
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass) {
+        render_pass.set_pipeline(&self.render_pipeline);
+        render_pass.set_bind_group(0, &self.render_bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+
+    /// Color the results into `target`, a texture of the format passed to `new`
+    pub fn render_to(&self, target: &wgpu::TextureView) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: target,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            self.draw(&mut render_pass);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Results of the view, row-major, blocking until the GPU is done
+    pub fn read_results(&self) -> Vec<[f32; 4]> {
+        let (width, height) = self.size();
+        readback::read_texture(
+            &self.device,
+            &self.queue,
+            &self.storage_texture,
+            width,
+            height,
+        )
+    }
+
+    /// Velocities of the view's particles when captured, see `read_results`
+    pub fn read_velocities(&self) -> Vec<[f32; 2]> {
+        let (width, height) = self.size();
+        readback::read_texture(
+            &self.device,
+            &self.queue,
+            &self.velocity_texture,
+            width,
+            height,
+        )
+    }
+
+    /// Boundary mask of the view, see `read_results`
+    pub fn read_boundaries(&self) -> Vec<f32> {
+        let (width, height) = self.size();
+        readback::read_texture(
+            &self.device,
+            &self.queue,
+            &self.boundary_texture,
+            width,
+            height,
+        )
+    }
+
+    /// Replace the results and velocities, e.g. with `cpu::render_with_velocities`, and rebuild
+    /// the boundary mask. Rows are `row_length` long, `data.width` for a render of the whole view
+    /// or the width of `size` for `read_results`, only the part of the view the textures hold is
+    /// written
+    pub fn write_results(&self, texels: &[[f32; 4]], velocities: &[[f32; 2]], row_length: u32) {
+        self.write_texture(&self.storage_texture, texels, row_length);
+        self.write_texture(&self.velocity_texture, velocities, row_length);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Boundary Encoder"),
+            });
+        self.compute_boundaries(&mut encoder);
+        self.queue.submit(std::iter::once(encoder.finish()));
+    }
+
+    /// Write the part of the view the textures hold from `texels` in rows of `row_length`
+    fn write_texture<T: Pod>(&self, texture: &wgpu::Texture, texels: &[T], row_length: u32) {
+        let (width, height) = self.size();
+        assert!(
+            row_length >= width && texels.len() >= (row_length * height) as usize,
+            "{} texels in rows of {row_length} don't cover {width}x{height}",
+            texels.len()
+        );
+        self.queue.write_texture(
+            texture.as_image_copy(),
+            bytemuck::cast_slice(texels),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(row_length * size_of::<T>() as u32),
+                rows_per_image: None,
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+
+    /// Replace the boundary mask with `mask`, laid out like `read_boundaries`
+    pub fn write_boundaries(&self, mask: &[f32]) {
+        let (width, height) = self.size();
+        assert_eq!(mask.len(), (width * height) as usize);
+        self.queue.write_texture(
+            self.boundary_texture.as_image_copy(),
+            bytemuck::cast_slice(mask),
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width * size_of::<f32>() as u32),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+    }
+}
@@ -0,0 +1,446 @@
+//! Scene files: bodies, solver settings and optionally a camera, stored as JSON.
+//! Scenes are edited on the host and converted to the GPU layout of `Data` with `Scene::to_data`
+//! or `Scene::apply`.
+
+use crate::{camera::Camera, generate::Generator, palette::Palette};
+use anyhow::{bail, Context};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use shader::{Data, Graviton, MAX_GRAVITONS};
+use spirv_std::glam::{dvec2, DVec2, Vec2, Vec3};
+use std::{collections::HashSet, fs, path::Path};
+
+/// Default solver settings without gravitons
+fn empty_data() -> Data {
+    Data::new([Graviton::default(); MAX_GRAVITONS], 0)
+}
+
+/// glam vectors as plain arrays in scene files
+macro_rules! array_serde {
+    ($module:ident, $vector:ty, $array:ty) => {
+        mod $module {
+            use super::*;
+
+            pub fn serialize<S: Serializer>(
+                vector: &$vector,
+                serializer: S,
+            ) -> Result<S::Ok, S::Error> {
+                vector.to_array().serialize(serializer)
+            }
+
+            pub fn deserialize<'de, D: Deserializer<'de>>(
+                deserializer: D,
+            ) -> Result<$vector, D::Error> {
+                <$array>::deserialize(deserializer).map(<$vector>::from_array)
+            }
+        }
+    };
+}
+array_serde!(vec2_array, Vec2, [f32; 2]);
+array_serde!(vec3_array, Vec3, [f32; 3]);
+array_serde!(dvec2_array, DVec2, [f64; 2]);
+
+/// A graviton of a scene
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Body {
+    /// stays with the body when others are added or removed, for tools referring to it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(with = "vec2_array")]
+    pub position: Vec2,
+    pub mass: f32,
+    /// linear RGB
+    #[serde(with = "vec3_array")]
+    pub color: Vec3,
+}
+impl Body {
+    pub fn new(position: Vec2, mass: f32, color: Vec3) -> Self {
+        Self {
+            id: None,
+            name: None,
+            position,
+            mass,
+            color,
+        }
+    }
+
+    pub fn to_graviton(&self) -> Graviton {
+        let [r, g, b] = self.color.to_array();
+        Graviton::new(self.position.x, self.position.y, r, g, b, self.mass)
+    }
+
+    /// Name, id or index `i` for messages
+    fn label(&self, i: usize) -> String {
+        match (&self.name, self.id) {
+            (Some(name), _) => format!("'{name}'"),
+            (None, Some(id)) => format!("with id {id}"),
+            (None, None) => i.to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SceneCamera {
+    #[serde(with = "dvec2_array")]
+    pub center: DVec2,
+    /// world units per pixel
+    pub scale: f64,
+}
+
+/// `shader::Integrator` by name
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "snake_case")]
+pub enum Integrator {
+    #[default]
+    Rk4,
+    Verlet,
+    Euler,
+}
+impl Integrator {
+    pub const ALL: [Self; 3] = [Self::Rk4, Self::Verlet, Self::Euler];
+
+    pub fn from_data(value: u32) -> Self {
+        match value {
+            shader::Integrator::VERLET => Self::Verlet,
+            shader::Integrator::EULER => Self::Euler,
+            _ => Self::Rk4,
+        }
+    }
+
+    pub fn to_data(self) -> u32 {
+        match self {
+            Self::Rk4 => shader::Integrator::RK4,
+            Self::Verlet => shader::Integrator::VERLET,
+            Self::Euler => shader::Integrator::EULER,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Solver {
+    pub capture_radius: f32,
+    pub max_steps: u32,
+    pub min_dt: f32,
+    pub max_dt: f32,
+    pub integrator: Integrator,
+}
+impl Default for Solver {
+    fn default() -> Self {
+        Self::from_data(&empty_data())
+    }
+}
+impl Solver {
+    pub fn from_data(data: &Data) -> Self {
+        Self {
+            capture_radius: data.capture_radius,
+            max_steps: data.max_steps,
+            min_dt: data.min_dt,
+            max_dt: data.max_dt,
+            integrator: Integrator::from_data(data.integrator),
+        }
+    }
+
+    /// Overwrite the solver settings of `data`
+    pub fn apply(&self, data: &mut Data) {
+        data.capture_radius = self.capture_radius;
+        data.max_steps = self.max_steps;
+        data.min_dt = self.min_dt;
+        data.max_dt = self.max_dt;
+        data.integrator = self.integrator.to_data();
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Scene {
+    /// older scene files call them gravitons
+    #[serde(default, alias = "gravitons")]
+    pub bodies: Vec<Body>,
+    /// run in order after `bodies` when loading, adding to them
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub generators: Vec<Generator>,
+    #[serde(default)]
+    pub solver: Solver,
+    /// fitted to the gravitons if missing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub camera: Option<SceneCamera>,
+    /// what `generate` started from, see `snapshot`
+    #[serde(skip)]
+    generated: Option<Generated>,
+}
+
+/// Bodies and generators of a scene file, and the bodies the generators made of them
+#[derive(Clone, Debug)]
+struct Generated {
+    bodies: Vec<Body>,
+    generators: Vec<Generator>,
+    result: Vec<Body>,
+}
+impl Scene {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let text = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read scene {}", path.display()))?;
+        let mut scene: Self = serde_json::from_str(&text)
+            .with_context(|| format!("Failed to parse scene {}", path.display()))?;
+        scene
+            .generate(&Palette::builtin()[0])
+            .and_then(|()| scene.check())
+            .with_context(|| format!("Invalid scene {}", path.display()))?;
+        Ok(scene)
+    }
+
+    /// Run the generators, leaving only bodies
+    pub fn generate(&mut self, palette: &Palette) -> anyhow::Result<()> {
+        if self.generators.is_empty() {
+            return Ok(());
+        }
+        let bodies = self.bodies.clone();
+        for generator in &self.generators {
+            generator.apply(&mut self.bodies, palette)?;
+        }
+        self.generated = Some(Generated {
+            bodies,
+            generators: std::mem::take(&mut self.generators),
+            result: self.bodies.clone(),
+        });
+        Ok(())
+    }
+
+    /// Everything `to_data` can't represent or the solvers can't handle
+    pub fn check(&self) -> anyhow::Result<()> {
+        if !self.generators.is_empty() {
+            bail!("Generators have to run before the scene can be used, see `Scene::generate`");
+        }
+        if self.bodies.len() > MAX_GRAVITONS {
+            bail!(
+                "{} bodies, at most {MAX_GRAVITONS} are supported",
+                self.bodies.len()
+            );
+        }
+        let mut ids = HashSet::new();
+        for (i, body) in self.bodies.iter().enumerate() {
+            if !body.position.is_finite() || !body.mass.is_finite() || !body.color.is_finite() {
+                bail!(
+                    "Body {} has a position, mass or color that isn't finite",
+                    body.label(i)
+                );
+            }
+            if let Some(id) = body.id.filter(|&id| !ids.insert(id)) {
+                bail!("Id {id} is used by more than one body");
+            }
+        }
+        let solver = self.solver;
+        if !(solver.capture_radius > 0.) || solver.max_steps == 0 {
+            bail!("Capture radius and maximum steps have to be positive");
+        }
+        if !(solver.min_dt > 0. && solver.min_dt <= solver.max_dt) {
+            bail!(
+                "Step size bounds {}..{} are empty or not positive",
+                solver.min_dt,
+                solver.max_dt
+            );
+        }
+        if let Some(camera) = self.camera {
+            if !camera.center.is_finite() || !(camera.scale > 0.) {
+                bail!("Camera needs a finite center and a positive scale");
+            }
+        }
+        Ok(())
+    }
+
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let text = serde_json::to_string_pretty(self)?;
+        fs::write(path, text).with_context(|| format!("Failed to write scene {}", path.display()))
+    }
+
+    /// The scene as saved with a view through `camera`. Generated bodies are saved as the
+    /// generators that made them, unless they were edited since
+    pub fn snapshot(&self, camera: &Camera) -> Self {
+        let mut scene = self.clone();
+        if let Some(generated) = scene.generated.take() {
+            if generated.result == self.bodies {
+                scene.bodies = generated.bodies;
+                scene.generators = generated.generators;
+            }
+        }
+        scene.camera = Some(SceneCamera {
+            center: camera.center,
+            scale: camera.scale,
+        });
+        scene
+    }
+
+    /// Bodies and solver settings in the GPU layout, the view isn't set yet. Fails if `check` does
+    pub fn to_data(&self) -> anyhow::Result<Data> {
+        let mut data = empty_data();
+        self.apply(&mut data)?;
+        Ok(data)
+    }
+
+    /// Overwrite the bodies and solver settings of `data`, keeping its view and coloring.
+    /// Leaves `data` alone if `check` fails
+    pub fn apply(&self, data: &mut Data) -> anyhow::Result<()> {
+        self.check()?;
+        data.gravitons = [Graviton::default(); MAX_GRAVITONS];
+        data.num_gravitons = 0;
+        for body in &self.bodies {
+            data.add_graviton(body.to_graviton());
+        }
+        self.solver.apply(data);
+        Ok(())
+    }
+
+    /// The stored camera, or `fit_camera` if there is none
+    pub fn camera(&self, size: DVec2) -> Camera {
+        match self.camera {
+            Some(camera) => Camera {
+                center: camera.center,
+                scale: camera.scale,
+            },
+            None => self.fit_camera(size),
+        }
+    }
+
+    /// A camera showing all bodies in a view of `size` pixels
+    pub fn fit_camera(&self, size: DVec2) -> Camera {
+        let positions = self.bodies.iter().map(|body| body.position.as_dvec2());
+        let min = positions.clone().fold(DVec2::INFINITY, DVec2::min);
+        let max = positions.fold(DVec2::NEG_INFINITY, DVec2::max);
+        if min.x > max.x {
+            return Camera::new(DVec2::ZERO);
+        }
+        // leave as much room around the bodies as they span, at least 100 units
+        let extent = ((max - min) * 2.).max(dvec2(100., 100.));
+        Camera {
+            center: (min + max) * 0.5,
+            scale: (extent / size).max_element(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use spirv_std::glam::{vec2, vec3};
+
+    fn scene() -> Scene {
+        let mut bodies = vec![
+            Body::new(vec2(-100., 0.), 1., vec3(1., 0., 0.)),
+            Body::new(vec2(100., 50.), 2.5, vec3(0., 1., 0.)),
+            Body::new(vec2(0., -80.), 0.5, vec3(0., 0., 1.)),
+        ];
+        bodies[0].name = Some("sun".into());
+        bodies[1].id = Some(7);
+        Scene {
+            bodies,
+            ..Scene::default()
+        }
+    }
+
+    #[test]
+    fn check() {
+        assert!(scene().check().is_ok());
+
+        let mut crowded = scene();
+        crowded.bodies = vec![crowded.bodies[2].clone(); MAX_GRAVITONS + 1];
+        assert!(crowded.check().is_err());
+        crowded.bodies.pop();
+        assert!(crowded.check().is_ok());
+
+        let mut position = scene();
+        position.bodies[2].position.y = f32::NAN;
+        assert!(position.check().is_err());
+        let mut mass = scene();
+        mass.bodies[0].mass = f32::INFINITY;
+        assert!(mass.check().is_err());
+        let mut id = scene();
+        id.bodies[2].id = Some(7);
+        assert!(id.check().is_err());
+
+        // nothing is uploaded when the check fails
+        let mut data = scene().to_data().unwrap();
+        assert!(mass.apply(&mut data).is_err());
+        assert_eq!(data.num_gravitons, 3);
+    }
+
+    #[test]
+    fn data_round_trip() {
+        let scene = scene();
+        let data = scene.to_data().unwrap();
+        assert_eq!(data.num_gravitons as usize, scene.bodies.len());
+        for (body, graviton) in scene.bodies.iter().zip(&data.gravitons) {
+            assert_eq!(
+                vec2(graviton.position_x, graviton.position_y),
+                body.position
+            );
+            assert_eq!(graviton.mass, body.mass);
+            assert_eq!(
+                vec3(graviton.color_r, graviton.color_g, graviton.color_b),
+                body.color
+            );
+        }
+        assert_eq!(Solver::from_data(&data), scene.solver);
+
+        let camera = Camera {
+            center: dvec2(10., -20.),
+            scale: 0.5,
+        };
+        let snapshot = scene.snapshot(&camera);
+        assert_eq!(snapshot.bodies, scene.bodies);
+        assert!(snapshot.generators.is_empty());
+        let saved = snapshot.camera.unwrap();
+        assert_eq!((saved.center, saved.scale), (camera.center, camera.scale));
+    }
+
+    #[test]
+    fn snapshot_keeps_generators() {
+        let generator = Generator::Polygon {
+            sides: 4,
+            radius: 100.,
+            mass: 1.,
+            rotation: 0.,
+        };
+        let mut scene = scene();
+        let bodies = scene.bodies.clone();
+        scene.generators.push(generator.clone());
+        scene.generate(&Palette::builtin()[0]).unwrap();
+        assert_eq!(scene.bodies.len(), 7);
+        assert!(scene.check().is_ok());
+
+        let camera = Camera::new(DVec2::ZERO);
+        let snapshot = scene.snapshot(&camera);
+        assert_eq!(snapshot.bodies, bodies);
+        assert_eq!(snapshot.generators, [generator]);
+
+        // once edited, the generated bodies are saved as they are
+        scene.bodies[5].mass = 3.;
+        let snapshot = scene.snapshot(&camera);
+        assert_eq!(snapshot.bodies, scene.bodies);
+        assert!(snapshot.generators.is_empty());
+    }
+
+    #[test]
+    fn file_round_trip() {
+        let mut scene = scene();
+        scene.solver.integrator = Integrator::Verlet;
+        scene.camera = Some(SceneCamera {
+            center: dvec2(1., 2.),
+            scale: 3.,
+        });
+        let path = std::env::temp_dir().join(format!("scene-test-{}.json", std::process::id()));
+        scene.save(&path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        let loaded = Scene::load(&path);
+        fs::remove_file(&path).unwrap();
+        let loaded = loaded.unwrap();
+
+        assert_eq!(loaded.bodies, scene.bodies);
+        assert_eq!(loaded.solver, scene.solver);
+        let camera = loaded.camera.unwrap();
+        assert_eq!((camera.center, camera.scale), (dvec2(1., 2.), 3.));
+        // names and ids are only written when set
+        assert_eq!(text.matches("\"name\"").count(), 1);
+        assert_eq!(text.matches("\"id\"").count(), 1);
+    }
+}
@@ -0,0 +1,129 @@
+//! Screenshots that don't stall the viewer: the readback is checked once per frame and the
+//! files are written on their own thread
+
+use anyhow::Context;
+use gravity_basins::{
+    hdr,
+    palette::{linear_to_srgb_bytes, Palette},
+    readback::Readback,
+    scene::Scene,
+};
+use shader::{BoundaryMode, Data};
+use std::{
+    fs::File,
+    io::BufWriter,
+    path::PathBuf,
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+/// A screenshot waiting for its readback
+pub struct Screenshot {
+    results: Readback<[f32; 4]>,
+    /// only read if boundaries are shown
+    boundaries: Option<Readback<f32>>,
+    width: u32,
+    height: u32,
+    data: Data,
+    scene: Scene,
+    palette: Palette,
+    /// `.png` and `.json` are added
+    path: PathBuf,
+}
+impl Screenshot {
+    /// Start copying the part of the result and boundary textures in view, `scene` is saved next
+    /// to the image
+    pub fn start(
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        results: &wgpu::Texture,
+        boundaries: &wgpu::Texture,
+        data: &Data,
+        scene: Scene,
+        palette: &Palette,
+    ) -> Self {
+        let size = results.size();
+        let width = data.width.min(size.width);
+        let height = data.height.min(size.height);
+        let path = PathBuf::from(format!("screenshot-{}", timestamp()));
+        Self {
+            results: Readback::start(device, queue, results, width, height),
+            boundaries: (data.boundary_mode != BoundaryMode::OFF)
+                .then(|| Readback::start(device, queue, boundaries, width, height)),
+            width,
+            height,
+            data: *data,
+            scene,
+            palette: palette.clone(),
+            path,
+        }
+    }
+
+    /// Hand the screenshot to a thread writing the files once the readback is done,
+    /// otherwise give it back. The device has to be polled for the readback to finish.
+    pub fn finish_if_done(self) -> Option<Self> {
+        let boundaries_done = self.boundaries.as_ref().is_none_or(Readback::is_done);
+        if !self.results.is_done() || !boundaries_done {
+            return Some(self);
+        }
+        thread::spawn(move || {
+            let path = self.path.clone();
+            match self.save() {
+                Ok(()) => log::info!("Saved screenshot {}.png", path.display()),
+                Err(error) => log::error!("{error:#}"),
+            }
+        });
+        None
+    }
+
+    fn save(self) -> anyhow::Result<()> {
+        let results = self.results.finish()?;
+        let boundaries = self.boundaries.map(Readback::finish).transpose()?;
+        let colors = hdr::colorize(&results, boundaries.as_deref(), &self.data, &self.palette);
+
+        let path = self.path.with_extension("png");
+        let file =
+            File::create(&path).with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut encoder = png::Encoder::new(BufWriter::new(file), self.width, self.height);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+        let bytes: Vec<u8> = colors.into_iter().flat_map(linear_to_srgb_bytes).collect();
+        writer.write_image_data(&bytes)?;
+        writer.finish()?;
+
+        self.scene.save(&self.path.with_extension("json"))
+    }
+}
+
+/// UTC time as `YYYY-MM-DD-HHMMSS-mmm`, with milliseconds so screenshots taken in quick
+/// succession don't overwrite each other
+fn timestamp() -> String {
+    let since = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    let (seconds, milliseconds) = (since.as_secs(), since.subsec_millis());
+    let (days, time) = (seconds / 86400, seconds % 86400);
+    // civil date from days since the epoch, see http://howardhinnant.github.io/date_algorithms.html
+    let z = days as i64 + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!(
+        "{year:04}-{month:02}-{day:02}-{:02}{:02}{:02}-{milliseconds:03}",
+        time / 3600,
+        time / 60 % 60,
+        time % 60
+    )
+}
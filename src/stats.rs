@@ -0,0 +1,149 @@
+//! Area and capture time of each basin, computed from a readback of the result texture
+
+use std::fmt::Write;
+
+/// Pixels captured by one graviton
+#[derive(Clone, Debug, Default)]
+pub struct Basin {
+    pub pixels: u64,
+    pub mean_time: f64,
+    pub median_time: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BasinStats {
+    /// indexed like the gravitons
+    pub basins: Vec<Basin>,
+    pub uncaptured: u64,
+    /// world area of one pixel
+    pub pixel_area: f64,
+}
+impl BasinStats {
+    /// Count the texels of `results` (see `Capture::to_texel`) per graviton
+    pub fn new(results: &[[f32; 4]], num_gravitons: usize, pixel_area: f64) -> Self {
+        let mut times = vec![Vec::new(); num_gravitons];
+        let mut uncaptured = 0;
+        for &[label, _, time, _] in results {
+            match times.get_mut(label as usize) {
+                Some(times) if label >= 0. => times.push(time),
+                _ => uncaptured += 1,
+            }
+        }
+
+        let basins = times
+            .into_iter()
+            .map(|mut times| {
+                if times.is_empty() {
+                    return Basin::default();
+                }
+                times.sort_unstable_by(f32::total_cmp);
+                let middle = times.len() / 2;
+                let median_time = if times.len() % 2 == 0 {
+                    (times[middle - 1] as f64 + times[middle] as f64) * 0.5
+                } else {
+                    times[middle] as f64
+                };
+                Basin {
+                    pixels: times.len() as u64,
+                    mean_time: times.iter().map(|&time| time as f64).sum::<f64>()
+                        / times.len() as f64,
+                    median_time,
+                }
+            })
+            .collect();
+        Self {
+            basins,
+            uncaptured,
+            pixel_area,
+        }
+    }
+
+    pub fn total_pixels(&self) -> u64 {
+        self.basins.iter().map(|basin| basin.pixels).sum::<u64>() + self.uncaptured
+    }
+
+    /// Share of all pixels
+    pub fn fraction(&self, pixels: u64) -> f64 {
+        pixels as f64 / self.total_pixels().max(1) as f64
+    }
+
+    /// One row per graviton plus one for uncaptured pixels, times are empty where undefined
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("graviton,pixels,fraction,area,mean_time,median_time\n");
+        for (i, basin) in self.basins.iter().enumerate() {
+            let times = if basin.pixels > 0 {
+                format!("{},{}", basin.mean_time, basin.median_time)
+            } else {
+                ",".to_owned()
+            };
+            writeln!(
+                csv,
+                "{i},{},{},{},{times}",
+                basin.pixels,
+                self.fraction(basin.pixels),
+                basin.pixels as f64 * self.pixel_area,
+            )
+            .unwrap();
+        }
+        writeln!(
+            csv,
+            "uncaptured,{},{},{},,",
+            self.uncaptured,
+            self.fraction(self.uncaptured),
+            self.uncaptured as f64 * self.pixel_area,
+        )
+        .unwrap();
+        csv
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Three pixels in basin 0, four in basin 1, none in basin 2 and three uncaptured, one of
+    /// them labeled with a graviton that doesn't exist
+    fn stats() -> BasinStats {
+        let texel = |label: f32, time: f32| [label, 0., time, 0.];
+        let results = [
+            texel(0., 3.),
+            texel(1., 4.),
+            texel(0., 1.),
+            texel(-1., 0.),
+            texel(1., 1.),
+            texel(1., 3.),
+            texel(7., 5.),
+            texel(0., 2.),
+            texel(1., 2.),
+            texel(-1., 0.),
+        ];
+        BasinStats::new(&results, 3, 0.5)
+    }
+
+    #[test]
+    fn counts_and_times() {
+        let stats = stats();
+        let summary: Vec<_> = stats
+            .basins
+            .iter()
+            .map(|basin| (basin.pixels, basin.mean_time, basin.median_time))
+            .collect();
+        // the median of an even count is the mean of the middle two
+        assert_eq!(summary, [(3, 2., 2.), (4, 2.5, 2.5), (0, 0., 0.)]);
+        assert_eq!(stats.uncaptured, 3);
+        assert_eq!(stats.total_pixels(), 10);
+        assert_eq!(stats.fraction(4), 0.4);
+    }
+
+    #[test]
+    fn csv() {
+        assert_eq!(
+            stats().to_csv(),
+            "graviton,pixels,fraction,area,mean_time,median_time\n\
+             0,3,0.3,1.5,2,2\n\
+             1,4,0.4,2,2.5,2.5\n\
+             2,0,0,0,,\n\
+             uncaptured,3,0.3,1.5,,\n"
+        );
+    }
+}
@@ -0,0 +1,249 @@
+//! Basin regions traced from the label image with marching squares, written as SVG paths
+
+use crate::{camera::Camera, palette::linear_to_srgb_bytes};
+use shader::Data;
+use spirv_std::glam::{dvec2, ivec2, DVec2, IVec2};
+use std::{collections::HashMap, fmt::Write};
+
+/// Closed outlines of the set pixels of `mask`, in pixel coordinates with pixel centers at `.5`.
+/// All outlines wind the same way around the inside, so holes wind the other way.
+pub fn trace(mask: &[bool], width: usize, height: usize) -> Vec<Vec<DVec2>> {
+    let inside = |corner: IVec2| {
+        (0..width as i32).contains(&corner.x)
+            && (0..height as i32).contains(&corner.y)
+            && mask[corner.y as usize * width + corner.x as usize]
+    };
+
+    // Cells have pixel centers as corners, the grid reaches one pixel past the image so
+    // every outline is closed. Edge midpoints are kept doubled, as integers, and map to
+    // the midpoint the outline continues with.
+    let mut next = HashMap::new();
+    for y in -1..height as i32 {
+        for x in -1..width as i32 {
+            // clockwise from the top left, on screen
+            let corners = [
+                ivec2(x, y),
+                ivec2(x + 1, y),
+                ivec2(x + 1, y + 1),
+                ivec2(x, y + 1),
+            ];
+            let mut crossings = [(IVec2::ZERO, false); 4];
+            let mut count = 0;
+            for i in 0..4 {
+                let (a, b) = (corners[i], corners[(i + 1) % 4]);
+                if inside(a) != inside(b) {
+                    crossings[count] = (a + b, inside(b));
+                    count += 1;
+                }
+            }
+            // walking around the cell, each entry into the inside is paired with the exit
+            // following it, which keeps diagonal saddles apart
+            for i in 0..count {
+                let (start, entering) = crossings[i];
+                if entering {
+                    next.insert(start, crossings[(i + 1) % count].0);
+                }
+            }
+        }
+    }
+
+    let mut starts: Vec<IVec2> = next.keys().copied().collect();
+    starts.sort_unstable_by_key(|point| (point.y, point.x));
+    let mut outlines = Vec::new();
+    for start in starts {
+        let mut point = start;
+        let mut outline = Vec::new();
+        while let Some(end) = next.remove(&point) {
+            outline.push(point.as_dvec2() * 0.5 + 0.5);
+            point = end;
+        }
+        if !outline.is_empty() {
+            outlines.push(outline);
+        }
+    }
+    outlines
+}
+
+/// Ramer-Douglas-Peucker on a closed outline, no point is moved further than `tolerance`
+pub fn simplify(outline: &[DVec2], tolerance: f64) -> Vec<DVec2> {
+    if outline.len() < 4 {
+        return outline.to_vec();
+    }
+    // split the loop at the point farthest from the first one and simplify both halves
+    let far = (1..outline.len())
+        .max_by(|&i, &j| {
+            let distance = |k: usize| outline[0].distance_squared(outline[k]);
+            distance(i).total_cmp(&distance(j))
+        })
+        .unwrap();
+    let closed: Vec<_> = outline.iter().chain(&outline[..1]).copied().collect();
+    let mut keep = vec![false; closed.len()];
+    keep[0] = true;
+    keep[far] = true;
+
+    let mut spans = vec![(0, far), (far, outline.len())];
+    while let Some((first, last)) = spans.pop() {
+        let (a, b) = (closed[first], closed[last]);
+        let distance = |point: DVec2| {
+            let direction = b - a;
+            if direction == DVec2::ZERO {
+                point.distance(a)
+            } else {
+                direction.perp_dot(point - a).abs() / direction.length()
+            }
+        };
+        let farthest = (first + 1..last)
+            .map(|i| (i, distance(closed[i])))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+        if let Some((i, distance)) = farthest {
+            if distance > tolerance {
+                keep[i] = true;
+                spans.push((first, i));
+                spans.push((i, last));
+            }
+        }
+    }
+    outline
+        .iter()
+        .zip(keep)
+        .filter_map(|(&point, keep)| keep.then_some(point))
+        .collect()
+}
+
+fn hex(color: [f32; 3]) -> String {
+    let [r, g, b] = linear_to_srgb_bytes(color);
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// One filled path per basin of `results` (see `Capture::to_texel`) over black for uncaptured
+/// pixels, with the gravitons as circles of their capture radius.
+/// `tolerance` is in pixels, see `simplify`.
+pub fn basins_svg(
+    results: &[[f32; 4]],
+    width: u32,
+    height: u32,
+    data: &Data,
+    camera: &Camera,
+    tolerance: f64,
+) -> String {
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )
+    .unwrap();
+    writeln!(
+        svg,
+        r#"<rect width="{width}" height="{height}" fill="black"/>"#
+    )
+    .unwrap();
+
+    let gravitons = &data.gravitons[..data.num_gravitons as usize];
+    for (i, graviton) in gravitons.iter().enumerate() {
+        let mask: Vec<_> = results.iter().map(|texel| texel[0] == i as f32).collect();
+        let outlines = trace(&mask, width as usize, height as usize);
+        if outlines.is_empty() {
+            continue;
+        }
+        let mut path = String::new();
+        for outline in outlines {
+            for (j, point) in simplify(&outline, tolerance).into_iter().enumerate() {
+                let command = if j == 0 { 'M' } else { 'L' };
+                write!(path, "{command}{:.2} {:.2}", point.x, point.y).unwrap();
+            }
+            path.push('Z');
+        }
+        let color = hex([graviton.color_r, graviton.color_g, graviton.color_b]);
+        // a thin stroke in the fill color hides seams between neighbouring basins
+        writeln!(
+            svg,
+            r#"<path d="{path}" fill="{color}" fill-rule="evenodd" stroke="{color}" stroke-width="0.5" stroke-linejoin="round"/>"#
+        )
+        .unwrap();
+    }
+
+    let size = dvec2(data.width as f64, data.height as f64);
+    let radius = (data.capture_radius as f64 / camera.scale).max(2.);
+    for graviton in gravitons {
+        let position = dvec2(graviton.position_x as f64, graviton.position_y as f64);
+        let center = camera.world_to_pixel(position, size) + 0.5;
+        let color = hex([graviton.color_r, graviton.color_g, graviton.color_b]);
+        writeln!(
+            svg,
+            r#"<circle cx="{:.2}" cy="{:.2}" r="{radius:.2}" fill="{color}" stroke="white"/>"#,
+            center.x, center.y
+        )
+        .unwrap();
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Twice the area, the sign tells which way the outline winds
+    fn signed_area(outline: &[DVec2]) -> f64 {
+        let next = outline.iter().cycle().skip(1);
+        outline.iter().zip(next).map(|(a, b)| a.perp_dot(*b)).sum()
+    }
+
+    fn mask(width: usize, height: usize, set: impl Fn(usize, usize) -> bool) -> Vec<bool> {
+        (0..width * height)
+            .map(|i| set(i % width, i / width))
+            .collect()
+    }
+
+    #[test]
+    fn square() {
+        let outlines = trace(
+            &mask(4, 4, |x, y| (1..3).contains(&x) && (1..3).contains(&y)),
+            4,
+            4,
+        );
+        assert_eq!(outlines.len(), 1);
+        let outline = &outlines[0];
+        assert_eq!(outline.len(), 8);
+        // closed: every point is next to the one before it, the last one next to the first
+        let next = outline.iter().cycle().skip(1);
+        assert!(outline.iter().zip(next).all(|(a, b)| a.distance(*b) <= 1.));
+        // on the pixel edges, with the corners cut
+        assert!(outline
+            .iter()
+            .all(|point| [point.x, point.y].iter().any(|&c| c == 1. || c == 3.)));
+        assert_eq!(signed_area(outline).abs(), 7.);
+    }
+
+    #[test]
+    fn hole() {
+        let outlines = trace(&mask(5, 5, |x, y| (x, y) != (2, 2)), 5, 5);
+        assert_eq!(outlines.len(), 2);
+        let (outer, hole) = (signed_area(&outlines[0]), signed_area(&outlines[1]));
+        assert_eq!(outer.abs(), 49.);
+        assert_eq!(hole.abs(), 1.);
+        // winding the other way, so `evenodd` and `nonzero` both leave it empty
+        assert!(outer * hole < 0.);
+    }
+
+    #[test]
+    fn simplification() {
+        let outline = [
+            dvec2(0., 0.),
+            dvec2(1., 0.),
+            dvec2(2., 0.05),
+            dvec2(3., 0.),
+            dvec2(4., 0.),
+            dvec2(4., 4.),
+            dvec2(0., 4.),
+        ];
+        let corners = [dvec2(0., 0.), dvec2(4., 0.), dvec2(4., 4.), dvec2(0., 4.)];
+        assert_eq!(simplify(&outline, 0.1), corners);
+        // the bump is further than the tolerance, its neighbours are not
+        let mut bumped = corners.to_vec();
+        bumped.insert(1, dvec2(2., 0.05));
+        assert_eq!(simplify(&outline, 0.03), bumped);
+        assert_eq!(simplify(&outline, 0.), outline);
+        assert_eq!(simplify(&outline[..3], 10.), outline[..3]);
+    }
+}
@@ -0,0 +1,120 @@
+//! GPU time of compute passes from timestamp queries, on adapters with `TIMESTAMP_QUERY`
+
+use std::{
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+/// Timestamps written at the start and end of one pass, read back without stalling
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    /// nanoseconds per timestamp tick
+    period: f32,
+    /// copy of the resolved timestamps on its way to the CPU, set by `map_async`
+    pending: Option<(
+        wgpu::Buffer,
+        Arc<OnceLock<Result<(), wgpu::BufferAsyncError>>>,
+    )>,
+    last: Option<Duration>,
+}
+impl GpuTimer {
+    const SIZE: u64 = 2 * size_of::<u64>() as u64;
+
+    /// `None` if `device` was created without `TIMESTAMP_QUERY`
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("Timestamp Queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Resolve Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            period: queue.get_timestamp_period(),
+            pending: None,
+            last: None,
+        })
+    }
+
+    /// Writes for the pass to time, `None` while the last time is still being read back
+    pub fn timestamp_writes(&self) -> Option<wgpu::ComputePassTimestampWrites<'_>> {
+        self.pending
+            .is_none()
+            .then_some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(0),
+                end_of_pass_write_index: Some(1),
+            })
+    }
+
+    /// Read back the timestamps of a pass given `timestamp_writes`, once it is submitted
+    pub fn start_readback(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
+        let buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Timestamp Readback Buffer"),
+            size: Self::SIZE,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Timestamp Encoder"),
+        });
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(&self.resolve_buffer, 0, &buffer, 0, Self::SIZE);
+        queue.submit(std::iter::once(encoder.finish()));
+
+        let mapped = Arc::new(OnceLock::new());
+        let callback_mapped = mapped.clone();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                callback_mapped.set(result).ok();
+            });
+        self.pending = Some((buffer, mapped));
+    }
+
+    /// Duration of the last timed pass whose readback is done, polls `device` without waiting
+    pub fn time(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        if self.pending.is_some() {
+            if let Err(error) = device.poll(wgpu::PollType::Poll) {
+                log::error!("Failed to poll the device: {error}");
+            }
+        }
+        let done = self
+            .pending
+            .as_ref()
+            .is_some_and(|(_, mapped)| mapped.get().is_some());
+        if let Some((buffer, mapped)) = self.pending.take_if(|_| done) {
+            match mapped.get().unwrap() {
+                Ok(()) => {
+                    let timestamps: [u64; 2] =
+                        bytemuck::pod_read_unaligned(&buffer.slice(..).get_mapped_range());
+                    let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                    let nanoseconds = ticks as f64 * self.period as f64;
+                    self.last = Some(Duration::from_nanos(nanoseconds as u64));
+                }
+                Err(error) => log::error!("Failed to map timestamp buffer: {error}"),
+            }
+        }
+        self.last
+    }
+
+    /// Wait for the pending readback, see `time`
+    pub fn wait(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        if self.pending.is_some() {
+            device
+                .poll(wgpu::PollType::Wait)
+                .expect("Failed to wait for the timestamps");
+        }
+        self.time(device)
+    }
+}
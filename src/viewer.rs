@@ -0,0 +1,1179 @@
+//! Windowed front-end: a `BasinRenderer` in a winit window with the side panel and overlays
+
+use crate::{
+    gui::{self, Gui, Panel, Stats},
+    history::History,
+    inspector::Trajectory,
+    overlay::{self, Lines, Overlay, Toggles},
+    screenshot::Screenshot,
+};
+use anyhow::{bail, Context};
+use gravity_basins::{
+    analysis::{self, BoxCounting, UncertaintyExponent, Wada},
+    camera::Camera,
+    cpu, export,
+    generate::Generator,
+    gpu::{self, AdapterChoice},
+    hdr,
+    palette::Palette,
+    scene::{Body, Scene, Solver},
+    stats::BasinStats,
+    svg,
+    timing::GpuTimer,
+    BasinRenderer, Precision,
+};
+use shader::{BoundaryMode, ComputeMode, Data};
+use spirv_std::glam::{dvec2, vec2, vec3, DVec2};
+use std::{
+    sync::Arc,
+    time::{Duration, Instant},
+};
+use winit::{
+    application::ApplicationHandler,
+    event::*,
+    event_loop::ActiveEventLoop,
+    keyboard::{KeyCode, ModifiersState, PhysicalKey},
+    window::Window,
+};
+
+pub struct State {
+    surface: wgpu::Surface<'static>,
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+    config: wgpu::SurfaceConfiguration,
+    is_surface_configured: bool,
+    window: Arc<Window>,
+    /// scene being edited, the bodies and solver settings of `data` are rebuilt from it by `upload`
+    scene: Scene,
+    /// `scene` in the GPU layout, with the view and coloring settings
+    data: Data,
+    renderer: BasinRenderer,
+    compute_gravity_basins: bool,
+    precision: Precision,
+    camera: Camera,
+    cursor_position: DVec2,
+    overlay: Overlay,
+    overlay_toggles: Toggles,
+    /// world length of the scale bar, if shown
+    scale_bar_length: Option<f64>,
+    /// clicking traces a trajectory instead of selecting a graviton
+    inspecting: bool,
+    trajectory: Option<Trajectory>,
+    /// index of the selected graviton
+    selected: Option<usize>,
+    gui: Gui,
+    last_frame: Instant,
+    frame_time: Duration,
+    /// times the solver pass of the main view, if the adapter has timestamp queries
+    gpu_timer: Option<GpuTimer>,
+    history: History,
+    /// scene before the ui edit in progress, recorded once the pointer is released
+    pending_edit: Option<(Vec<Body>, Camera)>,
+    modifiers: ModifiersState,
+    palettes: Vec<Palette>,
+    /// palette last applied to the gravitons
+    graviton_palette: usize,
+    /// palette used by `ColorMode::CAPTURE_TIME`
+    shading_palette: usize,
+    /// path typed into the palette import field
+    palette_path: String,
+    /// statistics of the current results, if computed
+    basin_stats: Option<BasinStats>,
+    /// recompute the statistics whenever the results change
+    auto_stats: bool,
+    /// box-counting estimate for the current results
+    box_counting: Option<BoxCounting>,
+    /// uncertainty exponent for the current view
+    uncertainty_exponent: Option<UncertaintyExponent>,
+    /// Wada check of the current results
+    wada: Option<Wada>,
+    /// screenshots waiting for their readback
+    screenshots: Vec<Screenshot>,
+    /// settings of the generator in the side panel
+    generator: Generator,
+    /// camera of the position space view, while parameter space is shown
+    position_camera: Option<Camera>,
+    layout: Layout,
+    /// solver settings of the views compared to the main one
+    comparisons: Vec<Solver>,
+    /// one per comparison, kept in sync by `sync_comparisons`
+    comparison_renderers: Vec<BasinRenderer>,
+}
+
+/// Largest view the result textures hold
+pub const MAX_VIEW: [u32; 2] = [1920, 1080];
+
+/// How the window is shared between the main view and the comparison views
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Layout {
+    #[default]
+    Single,
+    /// side by side, the main view on the left
+    Split,
+    /// the main view, with the pixels labeled differently by the first comparison highlighted
+    Difference,
+}
+
+/// Three gravitons to start with when no scene is given, placed for a camera matching pixels
+fn default_scene() -> Scene {
+    let mut scene = Scene::default();
+    scene.bodies = vec![
+        Body::new(vec2(200., 100.), 1., vec3(1., 0., 0.)),
+        Body::new(vec2(300., 400.), 1., vec3(0., 1., 0.)),
+        Body::new(vec2(450., 50.), 1., vec3(0., 0., 1.)),
+    ];
+    scene
+}
+
+impl State {
+    async fn new(
+        window: Arc<Window>,
+        scene: Option<Scene>,
+        adapter_choice: &AdapterChoice,
+    ) -> anyhow::Result<State> {
+        let size = window.inner_size();
+
+        let instance = gpu::instance();
+        let surface = instance
+            .create_surface(window.clone())
+            .context("Failed to create a surface for the window")?;
+        let adapter =
+            gpu::select_adapter(&instance, adapter_choice, Some(&surface), MAX_VIEW).await?;
+        log::info!("Using {}", gpu::describe(&adapter));
+        let (device, queue) = gpu::request_device(&adapter).await?;
+
+        let surface_caps = surface.get_capabilities(&adapter);
+        if surface_caps.formats.is_empty() || surface_caps.alpha_modes.is_empty() {
+            bail!("{} can't present to the window", adapter.get_info().name);
+        }
+        // Shader code in this tutorial assumes an sRGB surface texture. Using a different
+        // one will result in all the colors coming out darker. If you want to support non
+        // sRGB surfaces, you'll need to account for that when drawing to the frame.
+        let surface_format = surface_caps
+            .formats
+            .iter()
+            .find(|f| f.is_srgb())
+            .copied()
+            .unwrap_or(surface_caps.formats[0]);
+
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: surface_format,
+            width: size.width,
+            height: size.height,
+            present_mode: wgpu::PresentMode::AutoVsync,
+            alpha_mode: surface_caps.alpha_modes[0],
+            view_formats: vec![],
+            desired_maximum_frame_latency: 2,
+        };
+
+        let window_size = dvec2(size.width as f64, size.height as f64);
+        let (camera, scene) = match scene {
+            Some(scene) => (scene.camera(window_size), scene),
+            // start with world coordinates matching pixels
+            None => (Camera::new(window_size * 0.5), default_scene()),
+        };
+        let mut data = scene.to_data()?;
+        camera.apply(&mut data, size.width, size.height);
+        let [max_width, max_height] = MAX_VIEW;
+        let mut renderer =
+            BasinRenderer::new(&device, &queue, config.format, max_width, max_height);
+        renderer.set_data(&data);
+        let palettes = Palette::builtin();
+        let shading_palette = palettes
+            .iter()
+            .position(|palette| palette.name == "viridis")
+            .unwrap_or(0);
+        renderer.set_palette(&palettes[shading_palette]);
+
+        let overlay = Overlay::new(&device, renderer.shader(), config.format);
+        let gui = Gui::new(&window, &device, config.format);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+
+        Ok(Self {
+            surface,
+            device,
+            queue,
+            config,
+            is_surface_configured: false,
+            window,
+            scene,
+            data,
+            renderer,
+            compute_gravity_basins: true,
+            precision: Precision::F32,
+            camera,
+            cursor_position: DVec2::ZERO,
+            overlay,
+            overlay_toggles: Toggles::default(),
+            scale_bar_length: None,
+            inspecting: false,
+            trajectory: None,
+            selected: None,
+            gui,
+            last_frame: Instant::now(),
+            frame_time: Duration::ZERO,
+            gpu_timer,
+            history: History::default(),
+            pending_edit: None,
+            modifiers: ModifiersState::empty(),
+            palettes,
+            graviton_palette: 0,
+            shading_palette,
+            palette_path: String::new(),
+            basin_stats: None,
+            auto_stats: false,
+            box_counting: None,
+            uncertainty_exponent: None,
+            wada: None,
+            screenshots: Vec::new(),
+            generator: Generator::defaults()[0].clone(),
+            position_camera: None,
+            layout: Layout::Single,
+            comparisons: Vec::new(),
+            comparison_renderers: Vec::new(),
+        })
+    }
+
+    /// Upload `data` with the current camera and recompute the basins
+    fn update(&mut self) {
+        let size = self.size();
+        self.camera
+            .apply(&mut self.data, size.x as u32, size.y as u32);
+        self.upload();
+        self.compute_gravity_basins = true;
+        self.update_overlay();
+        self.window.request_redraw();
+    }
+
+    /// Upload `data` without recomputing, for edits that only change how results are colored
+    fn update_colors(&mut self) {
+        self.upload();
+        self.update_overlay();
+        self.window.request_redraw();
+    }
+
+    /// Upload `data` to the main view and, with their own solver settings, to the comparisons
+    fn upload(&mut self) {
+        self.apply_scene();
+        let mut data = self.data;
+        if self.layout == Layout::Difference {
+            data.boundary_mode = BoundaryMode::DIFFERENCE;
+        }
+        self.renderer.set_data(&data);
+        let views = self.comparisons.iter().zip(&mut self.comparison_renderers);
+        for (i, (solver, renderer)) in views.enumerate() {
+            let mut data = self.data;
+            solver.apply(&mut data);
+            if self.layout == Layout::Split {
+                data.viewport_x = (i as u32 + 1) * data.width;
+            }
+            renderer.set_data(&data);
+        }
+    }
+
+    /// Rebuild the bodies and solver settings of `data` from `scene`, keeping the last ones that
+    /// passed `Scene::check` if it fails
+    fn apply_scene(&mut self) {
+        if let Err(error) = self.scene.apply(&mut self.data) {
+            log::error!("{error:#}");
+        }
+    }
+
+    /// Give every comparison a renderer, and the split layouts something to compare
+    fn sync_comparisons(&mut self) {
+        if self.layout != Layout::Single && self.comparisons.is_empty() {
+            self.comparisons.push(self.scene.solver);
+        }
+        self.comparison_renderers.truncate(self.comparisons.len());
+        while self.comparison_renderers.len() < self.comparisons.len() {
+            let [width, height] = MAX_VIEW;
+            let renderer =
+                BasinRenderer::new(&self.device, &self.queue, self.config.format, width, height);
+            renderer.set_palette(&self.palettes[self.shading_palette]);
+            self.comparison_renderers.push(renderer);
+        }
+    }
+
+    /// Views side by side
+    fn columns(&self) -> u32 {
+        match self.layout {
+            Layout::Split => 1 + self.comparisons.len() as u32,
+            Layout::Single | Layout::Difference => 1,
+        }
+    }
+
+    /// Comparison renderers solving a view in the current layout
+    fn active_comparisons(&self) -> &[BasinRenderer] {
+        let count = match self.layout {
+            Layout::Single => 0,
+            Layout::Split => self.comparison_renderers.len(),
+            Layout::Difference => 1,
+        };
+        &self.comparison_renderers[..count.min(self.comparison_renderers.len())]
+    }
+
+    /// Upload after an edit of `before`, recomputing only if the simulation changed
+    fn update_since(&mut self, before: &Data) {
+        self.apply_scene();
+        if bytemuck::bytes_of(&before.simulation()) == bytemuck::bytes_of(&self.data.simulation()) {
+            self.update_colors();
+        } else {
+            self.update();
+        }
+    }
+
+    /// Rebuild the overlay lines for the current camera
+    fn update_overlay(&mut self) {
+        let toggles = self.overlay_toggles;
+        let mut lines = Lines::new(self.camera, self.size());
+
+        // everything but the scale bar is in world space
+        if self.position_camera.is_none() {
+            if toggles.capture_radii {
+                lines.capture_radii(&self.data);
+            }
+            if toggles.gravitons {
+                lines.gravitons(&self.data);
+            }
+            if let Some(selected) = self.selected.filter(|_| toggles.selection) {
+                lines.selection(&self.data.gravitons[selected]);
+            }
+            if let Some(trajectory) = self.trajectory.as_ref().filter(|_| toggles.trajectory) {
+                lines.trajectory(trajectory, &self.data);
+            }
+        }
+        self.scale_bar_length = toggles.scale_bar.then(|| lines.scale_bar());
+
+        self.overlay.set_lines(&self.device, &lines);
+        self.update_title();
+    }
+
+    /// Show the readouts that don't fit into the overlay
+    fn update_title(&self) {
+        let mut title = format!("gravity basins - {:?}", self.precision);
+        if let Some(length) = self.scale_bar_length {
+            title += &format!(" - scale bar {length}");
+        }
+        if let Some(trajectory) = &self.trajectory {
+            title += &format!(" - {}", trajectory.summary());
+        }
+        self.window.set_title(&title);
+    }
+
+    /// Size of the main view, the window is shared with the comparisons in the split layout
+    fn size(&self) -> DVec2 {
+        let width = self.config.width / self.columns();
+        dvec2(width as f64, self.config.height as f64)
+    }
+
+    pub fn resize(&mut self, width: u32, height: u32) {
+        if width > 0 && height > 0 {
+            self.config.width = width;
+            self.config.height = height;
+            self.surface.configure(&self.device, &self.config);
+            self.is_surface_configured = true;
+            self.update();
+        }
+    }
+
+    fn handle_key(&mut self, event_loop: &ActiveEventLoop, code: KeyCode, is_pressed: bool) {
+        let pan = self.size() * 0.1;
+        match (code, is_pressed) {
+            (KeyCode::Escape, true) => event_loop.exit(),
+            (KeyCode::ArrowLeft, true) => self.pan(dvec2(-pan.x, 0.)),
+            (KeyCode::ArrowRight, true) => self.pan(dvec2(pan.x, 0.)),
+            (KeyCode::ArrowUp, true) => self.pan(dvec2(0., -pan.y)),
+            (KeyCode::ArrowDown, true) => self.pan(dvec2(0., pan.y)),
+            (KeyCode::Equal, true) => self.zoom(self.size() * 0.5, 2.),
+            (KeyCode::Minus, true) => self.zoom(self.size() * 0.5, 0.5),
+            (KeyCode::KeyR, true) => {
+                let before = (self.scene.bodies.clone(), self.camera);
+                self.camera = Camera::new(self.size() * 0.5);
+                self.record(before);
+                self.update();
+            }
+            (KeyCode::KeyZ, true) if self.modifiers.control_key() => {
+                if self.modifiers.shift_key() {
+                    self.redo();
+                } else {
+                    self.undo();
+                }
+            }
+            (KeyCode::KeyF, true) => self.cycle_precision(),
+            (KeyCode::KeyC, true) => self.render_cpu(),
+            (KeyCode::KeyS, true) if self.modifiers.control_key() => self.save_scene(),
+            (KeyCode::KeyS, true) => self.export_stats(),
+            (KeyCode::KeyD, true) => self.compute_dimension(),
+            (KeyCode::KeyU, true) => self.compute_uncertainty_exponent(),
+            (KeyCode::KeyW, true) => self.compute_wada(),
+            (KeyCode::KeyE, true) => self.export_results(),
+            (KeyCode::KeyV, true) => self.export_svg(),
+            (KeyCode::F12 | KeyCode::KeyP, true) => self.screenshot(),
+            (KeyCode::KeyH, true) if self.modifiers.shift_key() => {
+                self.export_image("basins.png".as_ref())
+            }
+            (KeyCode::KeyH, true) => self.export_image("basins.exr".as_ref()),
+            (KeyCode::KeyB, true) => {
+                self.data.boundary_mode = (self.data.boundary_mode + 1) % 3;
+                self.update_colors();
+            }
+            (KeyCode::KeyI, true) => {
+                self.inspecting = !self.inspecting;
+                if !self.inspecting {
+                    self.trajectory = None;
+                    self.update_overlay();
+                }
+            }
+            (KeyCode::Digit1, true) => self.toggle_overlay(|t| &mut t.gravitons),
+            (KeyCode::Digit2, true) => self.toggle_overlay(|t| &mut t.capture_radii),
+            (KeyCode::Digit3, true) => self.toggle_overlay(|t| &mut t.selection),
+            (KeyCode::Digit4, true) => self.toggle_overlay(|t| &mut t.scale_bar),
+            (KeyCode::Digit5, true) => self.toggle_overlay(|t| &mut t.trajectory),
+            (KeyCode::Digit6, true) => self.toggle_overlay(|t| &mut t.timings),
+            (KeyCode::Digit7, true) => self.toggle_overlay(|t| &mut t.basin_stats),
+            _ => {}
+        }
+    }
+
+    fn handle_scroll(&mut self, delta: MouseScrollDelta) {
+        let lines = match delta {
+            MouseScrollDelta::LineDelta(_, y) => y as f64,
+            // roughly one line per 40 pixels
+            MouseScrollDelta::PixelDelta(position) => position.y / 40.,
+        };
+        self.zoom(self.cursor_position, 1.25f64.powf(lines));
+    }
+
+    /// Record the edits since the scene was `before`
+    fn record(&mut self, before: (Vec<Body>, Camera)) {
+        let (bodies, camera) = before;
+        self.history.push(History::diff(
+            (&bodies, &camera),
+            (&self.scene.bodies, &self.camera),
+        ));
+    }
+
+    fn undo(&mut self) {
+        let before = (self.data, self.camera);
+        if self.history.undo(&mut self.scene.bodies, &mut self.camera) {
+            self.after_history_change(before);
+        }
+    }
+
+    fn redo(&mut self) {
+        let before = (self.data, self.camera);
+        if self.history.redo(&mut self.scene.bodies, &mut self.camera) {
+            self.after_history_change(before);
+        }
+    }
+
+    fn after_history_change(&mut self, (data, camera): (Data, Camera)) {
+        self.selected = self
+            .selected
+            .filter(|&selected| selected < self.scene.bodies.len());
+        if camera == self.camera {
+            self.update_since(&data);
+        } else {
+            self.update();
+        }
+    }
+
+    fn toggle_overlay(&mut self, toggle: impl FnOnce(&mut Toggles) -> &mut bool) {
+        let toggle = toggle(&mut self.overlay_toggles);
+        *toggle = !*toggle;
+        self.update_overlay();
+        self.window.request_redraw();
+    }
+
+    fn handle_click(&mut self, button: MouseButton) {
+        if button != MouseButton::Left {
+            return;
+        }
+        if self.position_camera.is_some() {
+            let point = self
+                .camera
+                .pixel_to_world(self.cursor_position, self.size());
+            self.leave_parameter_space(Some(point));
+        } else if self.inspecting {
+            self.inspect(self.cursor_position);
+        } else {
+            self.select(self.cursor_position);
+        }
+    }
+
+    /// Show how the basin of a particle from the fixed start changes with two scene parameters,
+    /// around their current values
+    fn enter_parameter_space(&mut self) {
+        let count = self.data.num_gravitons;
+        if count == 0 {
+            log::warn!("Parameter space needs at least one graviton");
+            return;
+        }
+        let data = &mut self.data;
+        data.parameter_x_graviton = data.parameter_x_graviton.min(count - 1);
+        data.parameter_y_graviton = data.parameter_y_graviton.min(count - 1);
+        let value = dvec2(
+            data.parameter(data.parameter_x, data.parameter_x_graviton) as f64,
+            data.parameter(data.parameter_y, data.parameter_y_graviton) as f64,
+        );
+        // show each parameter from zero to twice its value (or -1 to 1 around zero)
+        let span = value.abs().max(DVec2::ONE) * 2.;
+        let scale = span.x / self.size().x;
+        data.parameter_y_scale = (span.y / self.size().y / scale) as f32;
+        data.parameter_space = 1;
+        let center = dvec2(value.x, value.y / data.parameter_y_scale as f64);
+        self.position_camera = Some(self.camera);
+        self.camera = Camera { center, scale };
+        self.inspecting = false;
+        self.trajectory = None;
+        self.update();
+    }
+
+    /// Back to the position space view, with the parameters at `point` of parameter space if given
+    fn leave_parameter_space(&mut self, point: Option<DVec2>) {
+        let Some(camera) = self.position_camera.take() else {
+            return;
+        };
+        // only the parameters are recorded, the history doesn't know about parameter space
+        self.camera = camera;
+        let before = (self.scene.bodies.clone(), self.camera);
+        self.data.parameter_space = 0;
+        if let Some(point) = point {
+            let data = self.data.with_parameters(point.as_vec2());
+            for (body, graviton) in self.scene.bodies.iter_mut().zip(&data.gravitons) {
+                body.position = vec2(graviton.position_x, graviton.position_y);
+                body.mass = graviton.mass;
+            }
+            let x = data.parameter(data.parameter_x, data.parameter_x_graviton);
+            let y = data.parameter(data.parameter_y, data.parameter_y_graviton);
+            log::info!("Parameters set to {x}, {y}");
+        }
+        self.record(before);
+        self.update();
+    }
+
+    /// Select the graviton under `pixel`, or clear the selection if there is none
+    fn select(&mut self, pixel: DVec2) {
+        let capture_radius = self.data.capture_radius as f64 / self.camera.scale;
+        self.selected = self.data.gravitons[..self.data.num_gravitons as usize]
+            .iter()
+            .map(|graviton| {
+                let position = dvec2(graviton.position_x as f64, graviton.position_y as f64);
+                let distance = self
+                    .camera
+                    .world_to_pixel(position, self.size())
+                    .distance(pixel);
+                (
+                    distance,
+                    overlay::graviton_radius(graviton).max(capture_radius),
+                )
+            })
+            .enumerate()
+            .filter(|(_, (distance, radius))| distance <= radius)
+            .min_by(|(_, (a, _)), (_, (b, _))| a.total_cmp(b))
+            .map(|(i, _)| i);
+        self.update_overlay();
+        self.window.request_redraw();
+    }
+
+    /// Trace the trajectory of a particle released at `pixel`
+    fn inspect(&mut self, pixel: DVec2) {
+        let start = self.camera.pixel_to_world(pixel, self.size());
+        let trajectory = Trajectory::trace(start, &self.data);
+        log::info!("Trajectory from {start}: {}", trajectory.summary());
+        self.trajectory = Some(trajectory);
+        self.update_overlay();
+        self.window.request_redraw();
+    }
+
+    fn pan(&mut self, pixels: DVec2) {
+        self.camera.pan(pixels);
+        self.update();
+    }
+
+    fn zoom(&mut self, pixel: DVec2, factor: f64) {
+        self.camera.zoom_at(pixel, self.size(), factor);
+        self.update();
+    }
+
+    /// Switch to the next precision the adapter supports
+    fn cycle_precision(&mut self) {
+        self.precision = match self.precision {
+            Precision::F32 => Precision::DoubleFloat,
+            Precision::DoubleFloat if self.renderer.supports(Precision::F64) => Precision::F64,
+            Precision::DoubleFloat | Precision::F64 => Precision::F32,
+        };
+        log::info!("Precision: {:?}", self.precision);
+        self.update();
+    }
+
+    /// Replace the current image with one from the `f64` CPU reference renderer
+    fn render_cpu(&mut self) {
+        let start = Instant::now();
+        let (texels, velocities) = cpu::render_with_velocities(&self.data);
+        log::info!("CPU reference render took {:?}", start.elapsed());
+        self.renderer
+            .write_results(&texels, &velocities, self.data.width);
+
+        self.compute_gravity_basins = false;
+        self.after_results_change();
+        self.window.request_redraw();
+    }
+
+    fn after_results_change(&mut self) {
+        if self.auto_stats {
+            self.compute_stats();
+        } else {
+            self.basin_stats = None;
+        }
+        self.box_counting = None;
+        // `compute` rebuilt the boundary mask without the Wada points
+        if self.shows_wada() {
+            self.compute_wada();
+        } else {
+            self.wada = None;
+        }
+    }
+
+    /// Whether the boundary mask is drawn with Wada points, and has to be rebuilt with them
+    fn shows_wada(&self) -> bool {
+        self.data.boundary_mode == BoundaryMode::WADA && self.layout != Layout::Difference
+    }
+
+    /// Check which boundary pixels are Wada points and mark them in the boundary mask
+    fn compute_wada(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let (width, height) = self.renderer.size();
+        let results: Vec<[f32; 4]> = self.renderer.read_results();
+        let wada = Wada::new(&results, width as usize, height as usize);
+        log::info!("{}", wada.summary());
+
+        // same values as `cs_boundary` writes, plus 2 for Wada points
+        let mask: Vec<f32> = analysis::boundary_mask(&results, width as usize, height as usize)
+            .into_iter()
+            .zip(&wada.points)
+            .map(|(boundary, &wada_point)| match (boundary, wada_point) {
+                (_, true) => 2.,
+                (true, false) => 1.,
+                (false, false) => 0.,
+            })
+            .collect();
+        self.renderer.write_boundaries(&mask);
+        self.wada = Some(wada);
+        self.data.boundary_mode = BoundaryMode::WADA;
+        self.update_colors();
+    }
+
+    /// Fit the uncertainty exponent of the current view, rendering it once per epsilon
+    fn compute_uncertainty_exponent(&mut self) {
+        let mut data = self.data;
+        data.compute_mode = ComputeMode::UNCERTAINTY;
+        let fractions = UncertaintyExponent::EPSILONS
+            .into_iter()
+            .map(|epsilon| {
+                data.epsilon = epsilon;
+                self.renderer.set_data(&data);
+                self.compute_gravity_basins();
+                let results: Vec<[f32; 4]> = self.renderer.read_results();
+                (epsilon, analysis::uncertain_fraction(&results))
+            })
+            .collect();
+        let exponent = UncertaintyExponent::new(fractions);
+        log::info!("{}", exponent.summary());
+
+        // back to the results of the actual settings
+        self.update();
+        self.uncertainty_exponent = Some(exponent);
+    }
+
+    /// Estimate the box-counting dimension of the boundaries in view
+    fn compute_dimension(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let mask: Vec<f32> = self.renderer.read_boundaries();
+        let mask: Vec<_> = mask.into_iter().map(|value| value > 0.5).collect();
+        let (width, height) = self.renderer.size();
+        let box_counting = BoxCounting::new(&mask, width as usize, height as usize);
+        log::info!("{}", box_counting.summary());
+        self.box_counting = Some(box_counting);
+    }
+
+    /// Save the raw results in view for analysis elsewhere, see `export::export`
+    fn export_results(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let (width, height) = self.renderer.size();
+        let results: Vec<[f32; 4]> = self.renderer.read_results();
+        let velocities = self.renderer.read_velocities();
+        let scene = self.scene.snapshot(&self.camera);
+        let precision = format!("{:?}", self.precision);
+        let metadata = export::Metadata {
+            width,
+            height,
+            precision: &precision,
+            scene: &scene,
+        };
+        let prefix = std::path::Path::new("basins");
+        match export::export(
+            prefix,
+            export::Format::Npy,
+            &results,
+            &velocities,
+            &self.data,
+            &metadata,
+        ) {
+            Ok(()) => log::info!("Exported results to {}_*.npy", prefix.display()),
+            Err(error) => log::error!("{error:#}"),
+        }
+    }
+
+    /// Save the basins in view as vector paths
+    fn export_svg(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let (width, height) = self.renderer.size();
+        let results: Vec<[f32; 4]> = self.renderer.read_results();
+        let svg = svg::basins_svg(&results, width, height, &self.data, &self.camera, 0.5);
+        let path = "basins.svg";
+        match std::fs::write(path, svg) {
+            Ok(()) => log::info!("Saved basins to {path}"),
+            Err(error) => log::error!("Failed to save {path}: {error}"),
+        }
+    }
+
+    /// Save the colored view at full precision, see `hdr::save`
+    fn export_image(&mut self, path: &std::path::Path) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let (width, height) = self.renderer.size();
+        let results: Vec<[f32; 4]> = self.renderer.read_results();
+        let boundaries: Option<Vec<f32>> =
+            (self.data.boundary_mode != BoundaryMode::OFF).then(|| self.renderer.read_boundaries());
+        let colors = hdr::colorize(
+            &results,
+            boundaries.as_deref(),
+            &self.data,
+            &self.palettes[self.shading_palette],
+        );
+        match hdr::save(path, width, height, &colors, &results, &self.data) {
+            Ok(()) => log::info!("Saved image to {}", path.display()),
+            Err(error) => log::error!("{error:#}"),
+        }
+    }
+
+    /// Start a screenshot of the view, written by `poll_screenshots` once it's read back
+    fn screenshot(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        if self.data.width == 0 || self.data.height == 0 {
+            return;
+        }
+        self.screenshots.push(Screenshot::start(
+            &self.device,
+            &self.queue,
+            self.renderer.results_texture(),
+            self.renderer.boundary_texture(),
+            &self.data,
+            self.scene.snapshot(&self.camera),
+            &self.palettes[self.shading_palette],
+        ));
+    }
+
+    /// Check on pending screenshots without waiting for the GPU
+    fn poll_screenshots(&mut self) {
+        if self.screenshots.is_empty() {
+            return;
+        }
+        if let Err(error) = self.device.poll(wgpu::PollType::Poll) {
+            log::error!("Failed to poll the device: {error}");
+        }
+        self.screenshots = std::mem::take(&mut self.screenshots)
+            .into_iter()
+            .filter_map(Screenshot::finish_if_done)
+            .collect();
+    }
+
+    fn save_scene(&self) {
+        let path = std::path::Path::new("scene.json");
+        match self.scene.snapshot(&self.camera).save(path) {
+            Ok(()) => log::info!("Saved scene to {}", path.display()),
+            Err(error) => log::error!("{error:#}"),
+        }
+    }
+
+    /// Count the pixels of each basin in the current results
+    fn compute_stats(&mut self) {
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+        }
+        let results: Vec<[f32; 4]> = self.renderer.read_results();
+        self.basin_stats = Some(BasinStats::new(
+            &results,
+            self.data.num_gravitons as usize,
+            self.camera.scale * self.camera.scale,
+        ));
+    }
+
+    fn export_stats(&mut self) {
+        if self.basin_stats.is_none() {
+            self.compute_stats();
+        }
+        let Some(stats) = &self.basin_stats else {
+            return;
+        };
+        let path = "basin_stats.csv";
+        match std::fs::write(path, stats.to_csv()) {
+            Ok(()) => log::info!("Saved basin statistics to {path}"),
+            Err(error) => log::error!("Failed to save {path}: {error}"),
+        }
+    }
+
+    fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        self.window.request_redraw();
+
+        // We can't render unless the surface is configured
+        if !self.is_surface_configured {
+            return Ok(());
+        }
+
+        let now = Instant::now();
+        self.frame_time = now - self.last_frame;
+        self.last_frame = now;
+        self.poll_screenshots();
+
+        let gui_frame = self.run_gui();
+
+        if self.compute_gravity_basins {
+            self.compute_gravity_basins();
+            self.compute_gravity_basins = false;
+            self.after_results_change();
+        }
+
+        let output = self.surface.get_current_texture()?;
+        let view = output
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Render Encoder"),
+            });
+        let gui_commands = self
+            .gui
+            .prepare(&self.device, &self.queue, &mut encoder, &gui_frame);
+
+        {
+            let mut render_pass = encoder
+                .begin_render_pass(&wgpu::RenderPassDescriptor {
+                    label: Some("Render Pass"),
+                    color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                        view: &view,
+                        resolve_target: None,
+                        ops: wgpu::Operations {
+                            load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                            store: wgpu::StoreOp::Store,
+                        },
+                    })],
+                    depth_stencil_attachment: None,
+                    occlusion_query_set: None,
+                    timestamp_writes: None,
+                })
+                // egui needs a 'static render pass
+                .forget_lifetime();
+            let (width, height) = (self.size().x as f32, self.size().y as f32);
+            if self.layout == Layout::Split {
+                for (i, renderer) in self.active_comparisons().iter().enumerate() {
+                    render_pass.set_viewport((i + 1) as f32 * width, 0., width, height, 0., 1.);
+                    renderer.draw(&mut render_pass);
+                }
+            }
+            // the overlay belongs to the main view
+            render_pass.set_viewport(0., 0., width, height, 0., 1.);
+            self.renderer.draw(&mut render_pass);
+            self.overlay.draw(&mut render_pass);
+            let (full_width, full_height) = (self.config.width as f32, self.config.height as f32);
+            render_pass.set_viewport(0., 0., full_width, full_height, 0., 1.);
+            self.gui.draw(&mut render_pass, &gui_frame);
+        }
+
+        // submit will accept anything that implements IntoIter
+        self.queue.submit(
+            gui_commands
+                .into_iter()
+                .chain(std::iter::once(encoder.finish())),
+        );
+        self.gui.finish(gui_frame);
+        output.present();
+
+        Ok(())
+    }
+
+    /// Run the side panel and apply its edits
+    fn run_gui(&mut self) -> gui::Frame {
+        let bodies = self.scene.bodies.clone();
+        let solver = self.scene.solver;
+        let data = self.data;
+        let camera = self.camera;
+        let precision = self.precision;
+        let overlay_toggles = self.overlay_toggles;
+        let selected = self.selected;
+        let shading_palette = self.shading_palette;
+        let (layout, comparisons) = (self.layout, self.comparisons.clone());
+        let view_size = self.size();
+
+        let mut panel = Panel {
+            scene: &mut self.scene,
+            data: &mut self.data,
+            camera: &mut self.camera,
+            precision: &mut self.precision,
+            f64_available: self.renderer.supports(Precision::F64),
+            view_size,
+            overlay_toggles: &mut self.overlay_toggles,
+            selected: &mut self.selected,
+            generator: &mut self.generator,
+            layout: &mut self.layout,
+            comparisons: &mut self.comparisons,
+            palettes: &mut self.palettes,
+            graviton_palette: &mut self.graviton_palette,
+            shading_palette: &mut self.shading_palette,
+            palette_path: &mut self.palette_path,
+            basin_stats: self.basin_stats.as_ref(),
+            auto_stats: &mut self.auto_stats,
+            compute_stats: false,
+            export_stats: false,
+            box_counting: self.box_counting.as_ref(),
+            compute_dimension: false,
+            uncertainty_exponent: self.uncertainty_exponent.as_ref(),
+            compute_uncertainty_exponent: false,
+            wada: self.wada.as_ref(),
+            compute_wada: false,
+            export_results: false,
+            export_svg: false,
+            export_image: None,
+            toggle_parameter_space: false,
+            stats: Stats {
+                frame_time: self.frame_time,
+                compute_time: self
+                    .gpu_timer
+                    .as_mut()
+                    .and_then(|timer| timer.time(&self.device)),
+                width: self.config.width,
+                height: self.config.height,
+                scale_bar_length: self.scale_bar_length,
+            },
+        };
+        let frame = self.gui.run(&self.window, |context| panel.show(context));
+        let (compute_stats, export_stats) = (panel.compute_stats, panel.export_stats);
+        let (compute_dimension, compute_uncertainty_exponent, compute_wada) = (
+            panel.compute_dimension,
+            panel.compute_uncertainty_exponent,
+            panel.compute_wada,
+        );
+        let (export_results, export_svg) = (panel.export_results, panel.export_svg);
+        let export_image = panel.export_image;
+        let toggle_parameter_space = panel.toggle_parameter_space;
+
+        let scene_changed = bodies != self.scene.bodies || solver != self.scene.solver;
+        if bodies != self.scene.bodies || camera != self.camera {
+            // a drag changes the scene every frame, but should only be one entry in the history
+            self.pending_edit.get_or_insert((bodies, camera));
+        }
+        if !self.gui.is_using_pointer() {
+            if let Some(before) = self.pending_edit.take() {
+                self.record(before);
+            }
+        }
+
+        if shading_palette != self.shading_palette {
+            let palette = &self.palettes[self.shading_palette];
+            self.renderer.set_palette(palette);
+            for renderer in &self.comparison_renderers {
+                renderer.set_palette(palette);
+            }
+        }
+        let views_changed = layout != self.layout || comparisons != self.comparisons;
+        if views_changed {
+            self.sync_comparisons();
+        }
+        if camera != self.camera || precision != self.precision || views_changed {
+            self.update();
+        } else if scene_changed || bytemuck::bytes_of(&data) != bytemuck::bytes_of(&self.data) {
+            self.update_since(&data);
+        } else if overlay_toggles != self.overlay_toggles || selected != self.selected {
+            self.update_overlay();
+        }
+
+        if export_stats {
+            self.export_stats();
+        } else if compute_stats {
+            self.compute_stats();
+        }
+        if compute_dimension {
+            self.compute_dimension();
+        }
+        if compute_uncertainty_exponent {
+            self.compute_uncertainty_exponent();
+        }
+        // also when the Wada overlay was picked before running the check
+        if compute_wada || (self.shows_wada() && self.wada.is_none()) {
+            self.compute_wada();
+        }
+        if export_results {
+            self.export_results();
+        }
+        if export_svg {
+            self.export_svg();
+        }
+        if let Some(path) = export_image {
+            self.export_image(path.as_ref());
+        }
+        if toggle_parameter_space && self.position_camera.is_some() {
+            self.leave_parameter_space(None);
+        } else if toggle_parameter_space {
+            self.enter_parameter_space();
+        }
+        frame
+    }
+
+    fn compute_gravity_basins(&mut self) {
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("Compute Encoder"),
+            });
+        let timestamp_writes = self.gpu_timer.as_ref().and_then(GpuTimer::timestamp_writes);
+        let timed = timestamp_writes.is_some();
+        self.renderer
+            .compute_timed(&mut encoder, self.precision, timestamp_writes);
+        for renderer in self.active_comparisons() {
+            renderer.compute(&mut encoder, self.precision);
+        }
+        if let (Layout::Difference, [other, ..]) = (self.layout, self.active_comparisons()) {
+            self.renderer.compute_difference(&mut encoder, other);
+        }
+        self.queue.submit(std::iter::once(encoder.finish()));
+        if let Some(timer) = self.gpu_timer.as_mut().filter(|_| timed) {
+            timer.start_readback(&self.device, &self.queue);
+        }
+    }
+}
+
+pub struct App {
+    state: Option<State>,
+    /// scene to open once the window exists
+    scene: Option<Scene>,
+    adapter_choice: AdapterChoice,
+    /// why the event loop was exited early
+    error: Option<anyhow::Error>,
+}
+
+impl App {
+    pub fn new(scene: Option<Scene>, adapter_choice: AdapterChoice) -> Self {
+        Self {
+            state: None,
+            scene,
+            adapter_choice,
+            error: None,
+        }
+    }
+
+    /// The error that ended the event loop, if any
+    pub fn into_result(self) -> anyhow::Result<()> {
+        self.error.map_or(Ok(()), Err)
+    }
+}
+
+impl ApplicationHandler<State> for App {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        let window_attributes = Window::default_attributes();
+        let state = event_loop
+            .create_window(window_attributes)
+            .context("Failed to create the window")
+            .and_then(|window| {
+                let state = State::new(Arc::new(window), self.scene.take(), &self.adapter_choice);
+                pollster::block_on(state)
+            });
+        match state {
+            Ok(state) => self.state = Some(state),
+            Err(error) => {
+                self.error = Some(error);
+                event_loop.exit();
+            }
+        }
+    }
+
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, mut event: State) {
+        self.state = Some(event);
+    }
+
+    fn window_event(
+        &mut self,
+        event_loop: &ActiveEventLoop,
+        _window_id: winit::window::WindowId,
+        event: WindowEvent,
+    ) {
+        let state = match &mut self.state {
+            Some(canvas) => canvas,
+            None => return,
+        };
+
+        if state.gui.on_window_event(&state.window, &event) {
+            return;
+        }
+
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::ModifiersChanged(modifiers) => state.modifiers = modifiers.state(),
+            WindowEvent::Resized(size) => state.resize(size.width, size.height),
+            WindowEvent::RedrawRequested => {
+                match state.render() {
+                    Ok(_) => {}
+                    // Reconfigure the surface if it's lost or outdated
+                    Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                        let size = state.window.inner_size();
+                        state.resize(size.width, size.height);
+                    }
+                    Err(e) => {
+                        log::error!("Unable to render {}", e);
+                    }
+                }
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(code),
+                        state: key_state,
+                        ..
+                    },
+                ..
+            } => state.handle_key(event_loop, code, key_state.is_pressed()),
+            WindowEvent::CursorMoved { position, .. } => {
+                // side by side views share the camera, so every one of them can be used
+                state.cursor_position = dvec2(position.x % state.size().x, position.y)
+            }
+            WindowEvent::MouseWheel { delta, .. } => state.handle_scroll(delta),
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button,
+                ..
+            } => state.handle_click(button),
+            _ => {}
+        }
+    }
+}
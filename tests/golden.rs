@@ -0,0 +1,172 @@
+//! Golden image tests: the reference scenes in `tests/scenes` are solved at 64x64 and their labels
+//! compared with the images in `tests/golden`, made by the `f64` CPU reference.
+//!
+//! The `f32` and double-float solvers are also compiled for the host and checked on the CPU, then
+//! all GPU solvers run on a software adapter (lavapipe or llvmpipe through
+//! `force_fallback_adapter`). Without one the test fails, unless `GOLDEN_CPU_ONLY=1` limits it to
+//! the CPU checks. `BLESS_GOLDENS=1 cargo test --test golden` rewrites the goldens from the CPU
+//! reference.
+
+use gravity_basins::{
+    cpu,
+    gpu::{self, AdapterChoice},
+    scene::Scene,
+    shader::{self, double_float, Data},
+    BasinRenderer, Precision,
+};
+use spirv_std::glam::{dvec2, uvec2, Vec2, Vec4};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SIZE: u32 = 64;
+/// Share of pixels that may land in another basin than in the golden. `f32` moves well under
+/// half a percent at the boundaries, solving the same scene with Verlet instead of RK4 about 3%.
+const TOLERANCE: f64 = 0.01;
+
+fn root() -> &'static Path {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+}
+
+fn golden_path(name: &str) -> PathBuf {
+    root().join("tests/golden").join(format!("{name}.pgm"))
+}
+
+fn view(name: &str) -> Data {
+    let path = root().join("tests/scenes").join(format!("{name}.json"));
+    let scene = Scene::load(&path).unwrap();
+    let mut data = scene.to_data().unwrap();
+    scene
+        .camera(dvec2(SIZE as f64, SIZE as f64))
+        .apply(&mut data, SIZE, SIZE);
+    data
+}
+
+/// `solve` of the shader crate or one of its modules
+type Solve = fn(Vec2, &Data) -> (Vec4, Vec2);
+
+/// Texels of a solver compiled for the host, one pixel after the other
+fn render_on_cpu(data: &Data, solve: Solve) -> Vec<[f32; 4]> {
+    (0..SIZE * SIZE)
+        .map(|i| {
+            let offset = data.pixel_offset(uvec2(i % SIZE, i / SIZE));
+            solve(offset, data).0.to_array()
+        })
+        .collect()
+}
+
+/// Graviton index plus one, zero if not captured
+fn labels(results: &[[f32; 4]]) -> Vec<u8> {
+    results.iter().map(|texel| (texel[0] + 1.) as u8).collect()
+}
+
+/// Binary PGM, viewable as a grayscale image
+fn pgm_header() -> String {
+    format!("P5\n{SIZE} {SIZE}\n255\n")
+}
+
+fn write_pgm(path: &Path, labels: &[u8]) {
+    let mut bytes = pgm_header().into_bytes();
+    bytes.extend_from_slice(labels);
+    fs::write(path, bytes).unwrap();
+}
+
+fn read_pgm(path: &Path) -> Vec<u8> {
+    let bytes = fs::read(path).unwrap_or_else(|error| {
+        panic!(
+            "Failed to read {}: {error}, run with BLESS_GOLDENS=1 to create it",
+            path.display()
+        )
+    });
+    bytes
+        .strip_prefix(pgm_header().as_bytes())
+        .unwrap_or_else(|| panic!("{} isn't a {SIZE}x{SIZE} golden", path.display()))
+        .to_vec()
+}
+
+/// Compare with the golden of `name`, saving `labels` next to the test binaries if they differ
+fn check(name: &str, solver: &str, labels: &[u8]) {
+    let golden = read_pgm(&golden_path(name));
+    assert_eq!(golden.len(), labels.len());
+    let mismatches = golden
+        .iter()
+        .zip(labels)
+        .filter(|(golden, label)| golden != label)
+        .count();
+    if mismatches as f64 > TOLERANCE * labels.len() as f64 {
+        let path = Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("{name}-{solver}.pgm"));
+        write_pgm(&path, labels);
+        panic!(
+            "{name} with {solver}: {mismatches} of {} pixels differ from the golden, see {}",
+            labels.len(),
+            path.display()
+        );
+    }
+}
+
+/// Software adapter, with `SHADER_F64` if it has it
+fn fallback_device() -> anyhow::Result<(wgpu::Device, wgpu::Queue)> {
+    let choice = AdapterChoice {
+        adapter: None,
+        software: true,
+    };
+    pollster::block_on(async {
+        let adapter = gpu::select_adapter(&gpu::instance(), &choice, None, [SIZE, SIZE]).await?;
+        gpu::request_device(&adapter).await
+    })
+}
+
+fn golden(name: &str) {
+    let data = view(name);
+    let reference = labels(&cpu::render(&data));
+    if std::env::var_os("BLESS_GOLDENS").is_some() {
+        write_pgm(&golden_path(name), &reference);
+        return;
+    }
+    check(name, "cpu", &reference);
+    // the GPU kernels' solvers, compiled for the host
+    let solvers: [(Solve, &str); 2] = [
+        (shader::solve, "cpu-f32"),
+        (double_float::solve, "cpu-double-float"),
+    ];
+    for (solve, solver) in solvers {
+        check(name, solver, &labels(&render_on_cpu(&data, solve)));
+    }
+
+    let (device, queue) = match fallback_device() {
+        Ok(device) => device,
+        Err(_) if std::env::var_os("GOLDEN_CPU_ONLY").is_some() => return,
+        Err(error) => panic!(
+            "{error:#}, install lavapipe or llvmpipe or run with GOLDEN_CPU_ONLY=1 to only check {name} on the CPU"
+        ),
+    };
+    let mut renderer =
+        BasinRenderer::new(&device, &queue, wgpu::TextureFormat::Rgba8Unorm, SIZE, SIZE);
+    renderer.set_data(&data);
+    for (precision, solver) in [
+        (Precision::F32, "f32"),
+        (Precision::DoubleFloat, "double-float"),
+        (Precision::F64, "f64"),
+    ] {
+        if renderer.supports(precision) {
+            renderer.submit_compute(precision);
+            check(name, solver, &labels(&renderer.read_results()));
+        }
+    }
+}
+
+#[test]
+fn triangle() {
+    golden("triangle");
+}
+
+#[test]
+fn unequal_masses() {
+    golden("unequal");
+}
+
+#[test]
+fn verlet() {
+    golden("verlet");
+}